@@ -1,38 +1,31 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
+use std::fs;
+use std::io::{self, IsTerminal};
 
 use super::flags::Flags;
+use crate::search::searcher::{SearcherBuilder, STDIN_PATH};
 
 #[derive(Debug)]
 pub struct Args {
-    pub needle: String,
+    pub patterns: Vec<String>,
     pub paths: Vec<String>,
-    pub _lua_script: Option<String>, // TODO: Add support for Lua scripts
+    pub lua_script: Option<String>,
+    pub lua_hooks: bool,
     pub flags: Flags,
 }
 
 impl Args {
-    pub fn walk_builder(&self) -> ignore::WalkBuilder {
-        let mut builder = ignore::WalkBuilder::new(&self.paths[0]);
-        for path in self.paths.iter().skip(1) {
-            builder.add(path);
-        }
-
+    /// Configure a searcher from the parsed command line.
+    pub fn searcher_builder(&self) -> SearcherBuilder {
+        let mut builder = SearcherBuilder::new();
         builder
-            .max_depth(self.flags.max_depth)
-            .hidden(!self.flags.hidden.is_enabled())
-            .types(
-                ignore::types::TypesBuilder::new()
-                    .add_defaults()
-                    .select("all")
-                    .build()
-                    .unwrap(),
-            );
-
+            .patterns(self.patterns.iter().cloned())
+            .flags(self.flags.clone());
         builder
     }
 }
 
-pub fn parse_args() -> Args {
+pub fn parse_args() -> io::Result<Args> {
     let matches = Command::new("getme")
         .version("0.1.0")
         .author("kortgrabb")
@@ -40,13 +33,29 @@ pub fn parse_args() -> Args {
         .arg(
             Arg::new("needle")
                 .help("The regex pattern to search for in the haystack")
-                .required(true)
+                .required_unless_present_any(["regexp", "file", "type-list"])
                 .index(1),
         )
+        .arg(
+            Arg::new("regexp")
+                .short('e')
+                .long("regexp")
+                .value_name("PATTERN")
+                .action(ArgAction::Append)
+                .help("A pattern to search for, can be given multiple times"),
+        )
+        .arg(
+            Arg::new("file")
+                .short('f')
+                .long("file")
+                .value_name("FILE")
+                .action(ArgAction::Append)
+                .help("Read patterns from FILE, one per line"),
+        )
         .arg(
             Arg::new("files")
-                .help("Files or directories to search")
-                .action(clap::ArgAction::Append)
+                .help("Files or directories to search, \"-\" reads standard input")
+                .action(ArgAction::Append)
                 .index(2),
         )
         .arg(
@@ -55,33 +64,91 @@ pub fn parse_args() -> Args {
                 .value_name("SCRIPT")
                 .help("Lua script to execute on search results"),
         )
+        .arg(
+            Arg::new("lua_hooks")
+                .long("lua-hooks")
+                .requires("lua_script")
+                .action(ArgAction::SetTrue)
+                .help("Run the --lua script before the search so it can define on_match, on_finish, filter and transform hooks"),
+        )
         .args(Flags::args())
         .get_matches();
 
-    // Extract pattern and files from matches.
-    let needle = matches
-        .get_one::<String>("needle")
-        .map(std::string::ToString::to_string)
-        .unwrap();
+    // Extract patterns and files from matches.
+    let mut patterns: Vec<String> = matches
+        .get_many::<String>("regexp")
+        .map(|values| values.map(|v| v.to_string()).collect())
+        .unwrap_or_default();
 
-    let files = matches
+    for pattern_file in matches.get_many::<String>("file").into_iter().flatten() {
+        let content = fs::read_to_string(pattern_file)
+            .map_err(|err| io::Error::new(err.kind(), format!("{pattern_file}: {err}")))?;
+        patterns.extend(content.lines().map(std::string::ToString::to_string));
+    }
+
+    let mut files: Vec<String> = matches
         .get_many::<String>("files")
         .map(|values| values.map(|v| v.to_string()).collect())
-        .unwrap_or(vec![".".to_string()]);
+        .unwrap_or_default();
 
-    // TODO: add pipeline support
+    let needle = matches
+        .get_one::<String>("needle")
+        .map(std::string::ToString::to_string);
+
+    // With -e or -f the first positional argument is a path, not a pattern
+    let explicit_patterns = matches.contains_id("regexp") || matches.contains_id("file");
+    match needle {
+        Some(path) if explicit_patterns => files.insert(0, path),
+        Some(needle) => patterns.push(needle),
+        None => {}
+    }
+
+    // Search piped input when no paths are given, the current directory otherwise
+    if files.is_empty() {
+        if stdin_is_readable() {
+            files.push(STDIN_PATH.to_string());
+        } else {
+            files.push(".".to_string());
+        }
+    }
 
     let lua_script = matches
         .get_one::<String>("lua_script")
         .map(std::string::ToString::to_string);
 
+    let lua_hooks = matches.get_flag("lua_hooks");
+
     // Extract flags from matches.
     let flags = Flags::from_matches(&matches);
 
-    Args {
-        needle,
+    Ok(Args {
+        patterns,
         paths: files,
-        _lua_script: lua_script,
+        lua_script,
+        lua_hooks,
         flags,
+    })
+}
+
+// Only search standard input if something is piped or redirected into it,
+// not when it is a terminal or closed like under cron or CI runners
+#[cfg(unix)]
+fn stdin_is_readable() -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    if io::stdin().is_terminal() {
+        return false;
     }
+
+    std::fs::metadata("/dev/stdin")
+        .map(|meta| {
+            let file_type = meta.file_type();
+            file_type.is_fifo() || file_type.is_file() || file_type.is_socket()
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn stdin_is_readable() -> bool {
+    !io::stdin().is_terminal()
 }
@@ -1,21 +1,86 @@
-use clap::{Arg, ArgAction, ArgMatches};
+use clap::{value_parser, Arg, ArgAction, ArgMatches};
 
 // allow more than 3 boolean flags clippy
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Flags {
     pub count: OptionState,
     pub no_file_lines: OptionState,
     pub no_file_names: OptionState, // TODO
     pub ignore_case: OptionState,
+    pub fixed_strings: OptionState,
     pub invert_match: OptionState,
     pub hidden: OptionState,
     pub list_files: OptionState,
     pub sequential: OptionState,
+    pub json: OptionState,
     pub max_depth: Option<usize>,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub label: Option<String>,
+    pub globs: Vec<GlobFlag>,
+    pub types: Vec<String>,
+    pub types_not: Vec<String>,
+    pub type_adds: Vec<String>,
+    pub type_list: OptionState,
+    pub replace: Option<String>,
+    pub write: OptionState,
+    pub dry_run: OptionState,
+    pub only_matching: OptionState,
+    pub column: OptionState,
+    pub byte_offset: OptionState,
+    pub vimgrep: OptionState,
+    pub sort: Option<SortFlag>,
+    pub files_without_match: OptionState,
+    pub quiet: OptionState,
+    pub max_count: Option<usize>,
+    pub max_total: Option<usize>,
+    pub word_regexp: OptionState,
+    pub line_regexp: OptionState,
+    pub smart_case: OptionState,
+    pub text: OptionState,
+    pub binary: OptionState,
+    pub encoding: Option<String>,
+    pub search_zip: OptionState,
 }
 
-#[derive(Debug, Default)]
+// A -g/--iglob filter, in the order given on the command line
+#[derive(Debug, Clone)]
+pub struct GlobFlag {
+    pub glob: String,
+    pub case_insensitive: bool,
+}
+
+// The order results are printed in with --sort or --sortr
+#[derive(Debug, Clone, Copy)]
+pub struct SortFlag {
+    pub key: SortKey,
+    pub reverse: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Path,
+    Modified,
+    Accessed,
+    Created,
+}
+
+impl SortKey {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Self::Path),
+            "modified" => Some(Self::Modified),
+            "accessed" => Some(Self::Accessed),
+            "created" => Some(Self::Created),
+            _ => None,
+        }
+    }
+}
+
+const SORT_KEYS: [&str; 4] = ["path", "modified", "accessed", "created"];
+
+#[derive(Debug, Default, Clone, Copy)]
 pub enum OptionState {
     #[default]
     Disabled,
@@ -62,11 +127,21 @@ impl Flags {
             ),
             flag!(
                 "recursive",
-                'r',
                 "recursive",
                 "Search all files in all subdirectories"
             ),
             flag!("count", 'c', "count", "Only show the number of matches"),
+            flag!(
+                "column",
+                "column",
+                "Show the 1-based column of the first match in each line"
+            ),
+            flag!(
+                "byte-offset",
+                'b',
+                "byte-offset",
+                "Show the byte offset of each line, or match with -o, in the file"
+            ),
             flag!("no-lines", "no-lines", "Add line numbers to output"),
             flag!(
                 "ignore-case",
@@ -74,7 +149,68 @@ impl Flags {
                 "ignore-case",
                 "Ignore case when searching"
             ),
+            flag!(
+                "text",
+                'a',
+                "text",
+                "Search files that look binary as if they were text"
+            ),
+            flag!(
+                "binary",
+                "binary",
+                "Search binary files to the end instead of stopping at the first match, so counts and stats are complete"
+            ),
+            flag!(
+                "search-zip",
+                'z',
+                "search-zip",
+                "Search inside gzip, xz, bzip2, zstd and lz4 compressed files"
+            )
+            .conflicts_with_all(["write", "dry-run"]),
+            flag!(
+                "smart-case",
+                'S',
+                "smart-case",
+                "Ignore case unless the pattern contains uppercase letters"
+            ),
+            flag!(
+                "fixed-strings",
+                'F',
+                "fixed-strings",
+                "Treat the pattern as a literal string instead of a regex"
+            ),
+            flag!(
+                "word-regexp",
+                'w',
+                "word-regexp",
+                "Only match the pattern as a whole word"
+            ),
+            flag!(
+                "line-regexp",
+                'x',
+                "line-regexp",
+                "Only match the pattern against the whole line"
+            ),
             flag!("list", 'l', "list", "Only show file names with matches"),
+            flag!(
+                "files-without-match",
+                'L',
+                "files-without-match",
+                "Only show file names without matches"
+            )
+            .conflicts_with_all(["json", "count", "list", "only-matching", "vimgrep"]),
+            flag!(
+                "only-matching",
+                'o',
+                "only-matching",
+                "Print only the matched parts of a line, each on its own line"
+            ),
+            flag!(
+                "quiet",
+                'q',
+                "quiet",
+                "Print nothing and stop at the first match, only set the exit code"
+            ),
             flag!(
                 "invert-match",
                 'v',
@@ -93,14 +229,145 @@ impl Flags {
                 "sequential",
                 "Search files sequentially instead of in parallel"
             ),
+            flag!(
+                "json",
+                "json",
+                "Print results as JSON Lines, one event per line"
+            )
+            .conflicts_with_all(["count", "list"]),
+            flag!(
+                "vimgrep",
+                "vimgrep",
+                "Print path:line:column:text once per match, for editor quickfix lists"
+            )
+            .conflicts_with_all(["json", "count", "list", "only-matching"]),
+            Arg::new("sort")
+                .long("sort")
+                .value_name("KEY")
+                .value_parser(SORT_KEYS)
+                .help("Print results sorted by path, modified, accessed or created"),
+            Arg::new("sortr")
+                .long("sortr")
+                .value_name("KEY")
+                .value_parser(SORT_KEYS)
+                .conflicts_with("sort")
+                .help("Like --sort but in reverse order"),
             Arg::new("max-depth")
                 .long("max-depth")
                 .value_name("DEPTH")
+                .value_parser(value_parser!(usize))
                 .help("Set the maximum depth for recursive search"),
+            Arg::new("max-count")
+                .short('m')
+                .long("max-count")
+                .value_name("NUM")
+                .value_parser(value_parser!(usize))
+                .help("Stop reading a file after NUM matching lines"),
+            Arg::new("max-total")
+                .long("max-total")
+                .value_name("NUM")
+                .value_parser(value_parser!(usize))
+                .help("Stop the whole search after NUM matching lines"),
+            Arg::new("after-context")
+                .short('A')
+                .long("after-context")
+                .value_name("NUM")
+                .value_parser(value_parser!(usize))
+                .help("Show NUM lines of context after each match"),
+            Arg::new("before-context")
+                .short('B')
+                .long("before-context")
+                .value_name("NUM")
+                .value_parser(value_parser!(usize))
+                .help("Show NUM lines of context before each match"),
+            Arg::new("context")
+                .short('C')
+                .long("context")
+                .value_name("NUM")
+                .value_parser(value_parser!(usize))
+                .help("Show NUM lines of context before and after each match"),
+            Arg::new("replace")
+                .short('r')
+                .long("replace")
+                .value_name("REPLACEMENT")
+                .help("Show matches replaced with REPLACEMENT, $1, $name or ${1}_suffix expand capture groups"),
+            flag!(
+                "write",
+                "write",
+                "Write the replacements back to the files instead of printing them"
+            )
+            .requires("replace")
+            .conflicts_with_all([
+                "json",
+                "count",
+                "list",
+                "files-without-match",
+                "invert-match",
+                "quiet",
+            ]),
+            flag!(
+                "dry-run",
+                "dry-run",
+                "Show what --write would change as a unified diff per file"
+            )
+            .requires("replace")
+            .conflicts_with_all([
+                "json",
+                "count",
+                "list",
+                "files-without-match",
+                "invert-match",
+                "quiet",
+            ]),
+            Arg::new("encoding")
+                .short('E')
+                .long("encoding")
+                .value_name("LABEL")
+                .help("Decode files from LABEL, e.g. utf-16le or shift_jis, unless they start with a BOM"),
+            Arg::new("label")
+                .long("label")
+                .value_name("LABEL")
+                .help("Name to show for standard input instead of <stdin>"),
+            Arg::new("glob")
+                .short('g')
+                .long("glob")
+                .value_name("GLOB")
+                .action(ArgAction::Append)
+                .help("Only search files matching GLOB, prefix with ! to exclude"),
+            Arg::new("iglob")
+                .long("iglob")
+                .value_name("GLOB")
+                .action(ArgAction::Append)
+                .help("Like --glob but ignores case"),
+            Arg::new("type")
+                .short('t')
+                .long("type")
+                .value_name("TYPE")
+                .action(ArgAction::Append)
+                .help("Only search files of TYPE, e.g. rust or js"),
+            Arg::new("type-not")
+                .short('T')
+                .long("type-not")
+                .value_name("TYPE")
+                .action(ArgAction::Append)
+                .help("Do not search files of TYPE"),
+            Arg::new("type-add")
+                .long("type-add")
+                .value_name("TYPE:GLOB")
+                .action(ArgAction::Append)
+                .help("Add a file type definition, e.g. 'proto:*.proto'"),
+            flag!(
+                "type-list",
+                "type-list",
+                "Show all known file types and their globs"
+            ),
         ]
     }
 
     pub fn from_matches(matches: &ArgMatches) -> Self {
+        // -A and -B take precedence over -C for their respective side.
+        let context = parse_count(matches, "context").unwrap_or(0);
+
         Self {
             no_file_names: if matches.get_flag("no-file-names") {
                 OptionState::Enabled
@@ -123,6 +390,11 @@ impl Flags {
             } else {
                 OptionState::Disabled
             },
+            fixed_strings: if matches.get_flag("fixed-strings") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
             invert_match: if matches.get_flag("invert-match") {
                 OptionState::Enabled
             } else {
@@ -143,10 +415,166 @@ impl Flags {
             } else {
                 OptionState::Disabled
             },
+            json: if matches.get_flag("json") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+
+            max_depth: parse_count(matches, "max-depth"),
+            max_count: parse_count(matches, "max-count"),
+            max_total: parse_count(matches, "max-total"),
+            before_context: parse_count(matches, "before-context").unwrap_or(context),
+            after_context: parse_count(matches, "after-context").unwrap_or(context),
+            label: matches.get_one::<String>("label").cloned(),
+            encoding: matches.get_one::<String>("encoding").cloned(),
+            replace: matches.get_one::<String>("replace").cloned(),
+            globs: parse_globs(matches),
+            types: parse_list(matches, "type"),
+            types_not: parse_list(matches, "type-not"),
+            type_adds: parse_list(matches, "type-add"),
+            type_list: if matches.get_flag("type-list") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            write: if matches.get_flag("write") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            dry_run: if matches.get_flag("dry-run") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            only_matching: if matches.get_flag("only-matching") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            column: if matches.get_flag("column") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            byte_offset: if matches.get_flag("byte-offset") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            vimgrep: if matches.get_flag("vimgrep") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            sort: parse_sort(matches),
+            files_without_match: if matches.get_flag("files-without-match") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            quiet: if matches.get_flag("quiet") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            text: if matches.get_flag("text") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            binary: if matches.get_flag("binary") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            search_zip: if matches.get_flag("search-zip") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            smart_case: if matches.get_flag("smart-case") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            word_regexp: if matches.get_flag("word-regexp") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+            line_regexp: if matches.get_flag("line-regexp") {
+                OptionState::Enabled
+            } else {
+                OptionState::Disabled
+            },
+        }
+    }
+
+    /// Returns true if files are edited in place rather than printed.
+    pub fn edits_files(&self) -> bool {
+        self.write.is_enabled() || self.dry_run.is_enabled()
+    }
+
+    /// Returns true if searching a file can stop at its first match.
+    pub fn search_once(&self) -> bool {
+        self.list_files.is_enabled()
+            || self.files_without_match.is_enabled()
+            || self.quiet.is_enabled()
+    }
+
+    /// Returns true if context lines should be collected around matches.
+    pub fn has_context(&self) -> bool {
+        (self.before_context > 0 || self.after_context > 0)
+            && !self.count.is_enabled()
+            && !self.vimgrep.is_enabled()
+            && !self.list_files.is_enabled()
+            && !self.files_without_match.is_enabled()
+            && !self.quiet.is_enabled()
+    }
+}
+
+fn parse_count(matches: &ArgMatches, name: &str) -> Option<usize> {
+    matches.get_one::<usize>(name).copied()
+}
 
-            max_depth: matches
-                .get_one::<String>("max-depth")
-                .map(|v| v.parse::<usize>().unwrap()),
+fn parse_list(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches
+        .get_many::<String>(name)
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+fn parse_sort(matches: &ArgMatches) -> Option<SortFlag> {
+    for (name, reverse) in [("sort", false), ("sortr", true)] {
+        if let Some(key) = matches.get_one::<String>(name) {
+            return SortKey::parse(key).map(|key| SortFlag { key, reverse });
         }
     }
+    None
+}
+
+// Merge -g and --iglob back into command line order, later globs win
+fn parse_globs(matches: &ArgMatches) -> Vec<GlobFlag> {
+    let mut globs: Vec<(usize, GlobFlag)> = Vec::new();
+
+    for (name, case_insensitive) in [("glob", false), ("iglob", true)] {
+        let (Some(indices), Some(values)) =
+            (matches.indices_of(name), matches.get_many::<String>(name))
+        else {
+            continue;
+        };
+
+        globs.extend(indices.zip(values).map(|(index, glob)| {
+            let glob = GlobFlag {
+                glob: glob.clone(),
+                case_insensitive,
+            };
+            (index, glob)
+        }));
+    }
+
+    globs.sort_by_key(|(index, _)| *index);
+    globs.into_iter().map(|(_, glob)| glob).collect()
 }
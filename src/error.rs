@@ -0,0 +1,93 @@
+use std::fmt;
+use std::io;
+
+/// Errors that can happen while building a searcher or searching.
+#[derive(Debug)]
+pub enum Error {
+    /// A pattern is not a valid regex.
+    Pattern(regex::Error),
+    /// The literal matcher could not be built from the patterns.
+    Literal(aho_corasick::BuildError),
+    /// The label given to `--encoding` names no known encoding.
+    Encoding(String),
+    /// A glob or file type filter is invalid.
+    Walk(ignore::Error),
+    /// A file could not be opened or read.
+    File { path: String, source: io::Error },
+    /// A file edited in place could not be written back.
+    Write { path: String, source: io::Error },
+    /// Writing the results failed.
+    Io(io::Error),
+    /// A Lua plugin failed to load or raised an error.
+    Lua(rlua::Error),
+}
+
+impl Error {
+    /// Wrap an I/O error that happened while reading `path`.
+    pub fn file(path: &str, source: io::Error) -> Self {
+        Self::File {
+            path: path.to_owned(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pattern(err) => write!(f, "invalid pattern: {err}"),
+            Self::Literal(err) => write!(f, "invalid literal: {err}"),
+            Self::Encoding(label) => write!(f, "unknown encoding: {label}"),
+            Self::Walk(err) => write!(f, "{err}"),
+            Self::File { path, source } => write!(f, "{path}: {source}"),
+            Self::Write { path, source } => write!(f, "{path}: cannot write: {source}"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Lua(err) => write!(f, "lua: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Pattern(err) => Some(err),
+            Self::Literal(err) => Some(err),
+            Self::Encoding(_) => None,
+            Self::Walk(err) => Some(err),
+            Self::File { source, .. } => Some(source),
+            Self::Write { source, .. } => Some(source),
+            Self::Io(err) => Some(err),
+            Self::Lua(err) => Some(err),
+        }
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Self::Pattern(err)
+    }
+}
+
+impl From<aho_corasick::BuildError> for Error {
+    fn from(err: aho_corasick::BuildError) -> Self {
+        Self::Literal(err)
+    }
+}
+
+impl From<ignore::Error> for Error {
+    fn from(err: ignore::Error) -> Self {
+        Self::Walk(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<rlua::Error> for Error {
+    fn from(err: rlua::Error) -> Self {
+        Self::Lua(err)
+    }
+}
@@ -0,0 +1,18 @@
+//! A simple grep implementation in rust.
+//!
+//! Build a [`Searcher`] with a [`SearcherBuilder`] and hand it a [`Sink`]
+//! that receives the matches, the context lines and the file boundaries.
+//! [`Printer`] and [`JsonPrinter`] are the sinks used by the `seek` binary.
+
+pub mod app;
+pub mod error;
+pub mod plugin_integration;
+pub mod search;
+
+pub use error::Error;
+pub use search::editor::{EditOutcome, EditSummary, FileEdit};
+pub use search::json::JsonPrinter;
+pub use search::printer::Printer;
+pub use search::result::{MatchKind, MatchSpan, SearchMatch, Stats};
+pub use search::searcher::{SearchResult, Searcher, SearcherBuilder};
+pub use search::sink::{Discard, Recorder, Sink};
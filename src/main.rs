@@ -1,12 +1,10 @@
-use app::args::{parse_args, Args};
-use app::flags::Flags;
-use search::searcher::{search_files, search_files_parallel, SearchResult};
+use seekr::app::args::{parse_args, Args};
+use seekr::plugin_integration::lua_plugin::{LuaPlugin, LuaSink};
+use seekr::search::printer::{format_diff, format_edit_summary, print_type_list};
+use seekr::search::searcher::type_definitions;
+use seekr::{Discard, EditOutcome, Error, JsonPrinter, Printer, SearchResult, Searcher, Sink};
+use std::io::Write;
 use std::process::ExitCode;
-use std::sync::atomic::AtomicBool;
-
-mod app;
-mod plugin_integration;
-mod search;
 
 /* Exit codes:
  * 0 - Matches found
@@ -17,6 +15,8 @@ mod search;
 fn main() -> ExitCode {
     match run() {
         Ok(code) => code,
+        // The reader went away (e.g. `seek foo | head`), nothing left to do
+        Err(err) if is_broken_pipe(err.as_ref()) => ExitCode::from(0),
         Err(err) => {
             eprintln!("Error: {err}");
             ExitCode::from(2)
@@ -25,17 +25,48 @@ fn main() -> ExitCode {
 }
 
 fn run() -> Result<ExitCode, Box<dyn std::error::Error>> {
-    let args = parse_args();
+    let args = parse_args()?;
+    if args.flags.type_list.is_enabled() {
+        print_type_list(&type_definitions(&args.flags)?)?;
+        return Ok(ExitCode::from(0));
+    }
+
+    let plugin = load_plugin(&args)?;
+
+    let mut builder = args.searcher_builder();
+    if let Some(plugin) = &plugin {
+        // Only make every search thread load the script if it has hooks
+        if plugin.has_hooks()? {
+            builder.lua_hooks(args.lua_script.clone());
+        }
+    }
+    let searcher = builder.build()?;
+
+    if args.flags.edits_files() {
+        return edit_with(&searcher, &args);
+    }
 
-    // Determine if multi-threaded search is needed based on flags.
-    let result = if args.flags.sequential.is_enabled() {
-        search_with(args, search_files)?
+    let result = if args.flags.quiet.is_enabled() {
+        search_with(&searcher, &args, plugin.as_ref(), &mut Discard)?
+    } else if args.flags.json.is_enabled() {
+        let mut printer = JsonPrinter::new();
+        let result = search_with(&searcher, &args, plugin.as_ref(), &mut printer)?;
+        printer.summary(&result.stats)?;
+        result
     } else {
-        search_with(args, search_files_parallel)?
+        let mut printer = Printer::new(&args.flags);
+        search_with(&searcher, &args, plugin.as_ref(), &mut printer)?
+    };
+
+    // With -L success means a file was listed, not that something matched
+    let success = if args.flags.files_without_match.is_enabled() {
+        result.stats.files_searched > result.stats.files_matched
+    } else {
+        result.has_match()
     };
 
     // Check if any matches were found.
-    if result.has_match() {
+    if success {
         Ok(ExitCode::from(0)) // Matches found
     } else {
         Ok(ExitCode::from(1)) // No matches found
@@ -44,25 +75,73 @@ fn run() -> Result<ExitCode, Box<dyn std::error::Error>> {
     // TODO: add modes
 }
 
+// Output written directly in main fails with a bare io::Error, the searcher
+// wraps it in Error::Io
+fn is_broken_pipe(err: &(dyn std::error::Error + 'static)) -> bool {
+    let io_err = match err.downcast_ref::<Error>() {
+        Some(Error::Io(io_err)) => Some(io_err),
+        _ => err.downcast_ref::<std::io::Error>(),
+    };
+    io_err.is_some_and(|io_err| io_err.kind() == std::io::ErrorKind::BrokenPipe)
+}
+
+fn load_plugin(args: &Args) -> Result<Option<LuaPlugin>, Error> {
+    let Some(script) = &args.lua_script else {
+        return Ok(None);
+    };
+
+    // Only scripts with hooks run before the search, the others get the
+    // collected results once it is done
+    let plugin = LuaPlugin::new();
+    if args.lua_hooks {
+        plugin.load_script(script)?;
+    }
+    Ok(Some(plugin))
+}
+
+// Replace matches in place, or print what would change with --dry-run
+fn edit_with(searcher: &Searcher, args: &Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let dry_run = args.flags.dry_run.is_enabled();
+    let mut out = std::io::stdout().lock();
+
+    let summary = searcher.edit_paths(&args.paths, |outcome| {
+        match outcome {
+            EditOutcome::Edited(edit) if dry_run => write!(out, "{}", format_diff(edit))?,
+            EditOutcome::Binary(path) => eprintln!("{path}: binary file, not edited"),
+            EditOutcome::Transcoded(path) => eprintln!("{path}: not UTF-8, not edited"),
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    writeln!(out, "{}", format_edit_summary(&summary, dry_run))?;
+    out.flush()?;
+
+    if summary.files_changed > 0 {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
 // Higher-level function to orchestrate search
-fn search_with<F>(args: Args, search_fn: F) -> Result<SearchResult, Box<dyn std::error::Error>>
-where
-    F: Fn(
-        &str,
-        &[String],
-        &Flags,
-        &ignore::WalkBuilder,
-        &AtomicBool,
-    ) -> Result<SearchResult, Box<dyn std::error::Error>>,
-{
-    let matched = AtomicBool::new(false);
-    let result = search_fn(
-        &args.needle,
-        &args.paths,
-        &args.flags,
-        &args.walk_builder(),
-        &matched,
-    )?;
+fn search_with<S: Sink>(
+    searcher: &Searcher,
+    args: &Args,
+    plugin: Option<&LuaPlugin>,
+    sink: &mut S,
+) -> Result<SearchResult, Error> {
+    let (Some(plugin), Some(script)) = (plugin, &args.lua_script) else {
+        return searcher.search_paths(&args.paths, sink);
+    };
+
+    // Matches go through the Lua plugin on their way to the printer
+    let result = searcher.search_paths(&args.paths, &mut LuaSink::new(plugin, sink))?;
+    if args.lua_hooks {
+        plugin.finish()?;
+    } else {
+        plugin.run_script(script)?;
+    }
 
     Ok(result)
 }
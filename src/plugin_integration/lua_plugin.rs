@@ -1,44 +1,135 @@
-use crate::search::result::SearchMatch;
+use crate::error::Error;
+use crate::search::result::{MatchKind, MatchSpan, SearchMatch, Stats};
+use crate::search::sink::Sink;
 use rlua::{Function, Lua, Result as LuaResult, Table};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs;
+use std::rc::Rc;
+
+/// Name of the function called for every match as it arrives.
+pub const ON_MATCH: &str = "on_match";
+/// Name of the function called with all matches once the search is done.
+pub const ON_FINISH: &str = "on_finish";
+/// Name of the function deciding whether a match is kept.
+pub const FILTER: &str = "filter";
+/// Name of the function rewriting a match before it is reported.
+pub const TRANSFORM: &str = "transform";
 
 pub struct LuaPlugin {
     pub lua: Lua,
 }
 
+impl Default for LuaPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LuaPlugin {
     pub fn new() -> Self {
         LuaPlugin { lua: Lua::new() }
     }
 
-    /// Load and execute the Lua script, providing the search results.
-    pub fn run_script(&self, script_path: &str, results: &[SearchMatch]) -> LuaResult<()> {
-        let script_content = fs::read_to_string(script_path)?;
-        let lua_ctx = &self.lua;
-        let lua_results_table = lua_ctx.create_table()?;
-        for (i, result) in results.iter().enumerate() {
-            let result_table = Self::create_result_table(lua_ctx, result)?;
-            lua_results_table.set(i + 1, result_table)?;
-        }
+    /// Load and execute the Lua script before the search starts, with
+    /// `--lua-hooks`.
+    ///
+    /// The global `results` table starts out empty and receives every match
+    /// as it is found. The script can define `on_match(result)` to see each
+    /// match as it arrives and `on_finish(results)` to report on all of them.
+    pub fn load_script(&self, script_path: &str) -> LuaResult<()> {
+        self.lua
+            .globals()
+            .set("results", self.lua.create_table()?)?;
+        self.exec_script(script_path)
+    }
+
+    /// Execute the Lua script once the search is done, with every match
+    /// collected in the global `results` table.
+    ///
+    /// Hooks defined by the script at this point could never be called, so
+    /// they are an error pointing at `--lua-hooks`.
+    pub fn run_script(&self, script_path: &str) -> LuaResult<()> {
+        self.results()?;
+        self.exec_script(script_path)?;
 
-        lua_ctx.globals().set("results", lua_results_table)?;
-        lua_ctx.load(&script_content).exec()?;
+        for hook in [ON_MATCH, ON_FINISH, FILTER, TRANSFORM] {
+            if self.has_function(hook)? {
+                return Err(rlua::Error::runtime(format!(
+                    "{script_path} defines {hook}, which is only called with --lua-hooks"
+                )));
+            }
+        }
         Ok(())
     }
 
-    /// Execute a callback Lua function for each search result
-    pub fn execute_callback(&self, callback_name: &str, results: &[SearchMatch]) -> LuaResult<()> {
-        let lua_ctx = &self.lua;
-        let callback: rlua::Function = lua_ctx.globals().get(callback_name)?;
+    fn exec_script(&self, script_path: &str) -> LuaResult<()> {
+        let script_content = fs::read_to_string(script_path)
+            .map_err(|err| rlua::Error::external(format!("{script_path}: {err}")))?;
+
+        self.lua
+            .load(&script_content)
+            .set_name(format!("@{script_path}"))
+            .exec()
+    }
 
-        for result in results {
-            let result_table = Self::create_result_table(lua_ctx, result)?;
-            callback.call::<_, ()>(result_table)?;
+    // The global `results` table, created on first use
+    fn results(&self) -> LuaResult<Table<'_>> {
+        let globals = self.lua.globals();
+        if let Some(results) = globals.get::<_, Option<Table>>("results")? {
+            return Ok(results);
         }
 
+        let results = self.lua.create_table()?;
+        globals.set("results", results.clone())?;
+        Ok(results)
+    }
+
+    /// Add a match to `results` and pass it to `on_match` if it is defined.
+    pub fn push_result(&self, result: &SearchMatch) -> LuaResult<()> {
+        let results = self.results()?;
+        let result_table = Self::create_result_table(&self.lua, result)?;
+        results.raw_set(results.raw_len() + 1, result_table.clone())?;
+
+        if self.has_function(ON_MATCH)? {
+            self.execute_callback(ON_MATCH, result_table)?;
+        }
+        Ok(())
+    }
+
+    /// Pass all collected matches to `on_finish` if it is defined.
+    pub fn finish(&self) -> LuaResult<()> {
+        if self.has_function(ON_FINISH)? {
+            self.execute_callback(ON_FINISH, self.results()?)?;
+        }
         Ok(())
     }
 
+    /// Returns true if the script defines `filter` or `transform`.
+    pub fn has_hooks(&self) -> LuaResult<bool> {
+        Ok(self.has_function(FILTER)? || self.has_function(TRANSFORM)?)
+    }
+
+    /// Ask the `filter` hook whether a match should be kept.
+    pub fn filter(&self, result: &SearchMatch) -> LuaResult<bool> {
+        let filter: Function = self.lua.globals().get(FILTER)?;
+        filter.call(Self::create_result_table(&self.lua, result)?)
+    }
+
+    /// Let the `transform` hook rewrite the line content and match spans.
+    pub fn transform(&self, result: SearchMatch) -> LuaResult<SearchMatch> {
+        let transform: Function = self.lua.globals().get(TRANSFORM)?;
+        let table: Table = transform.call(Self::create_result_table(&self.lua, &result)?)?;
+        Self::update_from_table(result, &table)
+    }
+
+    /// Execute a global Lua callback function with a single table argument
+    pub fn execute_callback(&self, callback_name: &str, argument: Table) -> LuaResult<()> {
+        let lua_ctx = &self.lua;
+        let callback: rlua::Function = lua_ctx.globals().get(callback_name)?;
+        callback.call::<_, ()>(argument)
+    }
+
     /// Check if the Lua context has a specific global function.
     pub fn has_function(&self, func_name: &str) -> LuaResult<bool> {
         let lua_ctx = &self.lua;
@@ -56,16 +147,160 @@ impl LuaPlugin {
         // Set the fields explicitly and ensure they are valid.
         lua_result.set("file", result.file.clone())?;
         lua_result.set("line_number", result.line_number)?;
-        lua_result.set("line_content", result.line_content.clone())?;
+        // Lua strings are byte strings, so lines with invalid UTF-8 pass through
+        lua_result.set("line_content", lua_ctx.create_string(&result.line_content)?)?;
         let matches_table = lua_ctx.create_table()?;
 
-        for (i, &(start, end)) in result.matches.iter().enumerate() {
+        for (i, span) in result.matches.iter().enumerate() {
             let match_table = lua_ctx.create_table()?;
-            match_table.set("start", start)?;
-            match_table.set("end", end)?;
+            match_table.set("start", span.start)?;
+            match_table.set("end", span.end)?;
+            match_table.set("pattern", span.pattern)?;
             matches_table.set(i + 1, match_table)?;
         }
+        lua_result.set("matches", matches_table)?;
 
         Ok(lua_result)
     }
+
+    /// Helper function to read a result table returned by a hook back into a match.
+    fn update_from_table(mut result: SearchMatch, table: &Table) -> LuaResult<SearchMatch> {
+        result.line_content = table
+            .get::<_, rlua::String>("line_content")?
+            .as_bytes()
+            .to_vec();
+
+        let mut spans = Vec::new();
+        for match_table in table.get::<_, Table>("matches")?.sequence_values::<Table>() {
+            let match_table = match_table?;
+            let span = MatchSpan::new(
+                match_table.get("start")?,
+                match_table.get("end")?,
+                match_table.get::<_, Option<usize>>("pattern")?.unwrap_or(0),
+            );
+
+            // Spans must be in order and disjoint, the printer and the editor
+            // slice the line between them
+            let previous_end = spans.last().map_or(0, |previous: &MatchSpan| previous.end);
+            let valid = previous_end <= span.start
+                && span.start <= span.end
+                && span.end <= result.line_content.len();
+            if !valid {
+                return Err(rlua::Error::runtime(format!(
+                    "{TRANSFORM} returned an invalid match span {}..{} for line {}",
+                    span.start, span.end, result.line_number
+                )));
+            }
+            spans.push(span);
+        }
+
+        result.matches = spans;
+        result.kind = MatchKind::Match;
+        Ok(result)
+    }
+}
+
+// A script loaded on the current thread, with the hooks it defines
+struct ThreadHooks {
+    plugin: LuaPlugin,
+    filter: bool,
+    transform: bool,
+}
+
+thread_local! {
+    // Lua states cannot be shared between threads, so each search thread
+    // loads its own copy of every script that has hooks
+    static THREAD_HOOKS: RefCell<HashMap<String, Rc<ThreadHooks>>> = RefCell::new(HashMap::new());
+}
+
+/// Runs the `filter` and `transform` hooks of a script while searching.
+///
+/// Every thread that searches files executes the script in its own Lua
+/// state, so the hooks cannot share state with each other or with
+/// `on_match` and `on_finish`.
+#[derive(Debug, Clone)]
+pub struct LuaHooks {
+    script_path: String,
+}
+
+impl LuaHooks {
+    pub fn new(script_path: &str) -> Self {
+        Self {
+            script_path: script_path.to_owned(),
+        }
+    }
+
+    fn thread_hooks(&self) -> LuaResult<Rc<ThreadHooks>> {
+        THREAD_HOOKS.with(|hooks| {
+            if let Some(loaded) = hooks.borrow().get(&self.script_path) {
+                return Ok(Rc::clone(loaded));
+            }
+
+            let plugin = LuaPlugin::new();
+            plugin.load_script(&self.script_path)?;
+            let loaded = Rc::new(ThreadHooks {
+                filter: plugin.has_function(FILTER)?,
+                transform: plugin.has_function(TRANSFORM)?,
+                plugin,
+            });
+            hooks
+                .borrow_mut()
+                .insert(self.script_path.clone(), Rc::clone(&loaded));
+            Ok(loaded)
+        })
+    }
+
+    /// Run the hooks on a match, returns `None` if `filter` dropped it.
+    pub fn apply(&self, result: SearchMatch) -> Result<Option<SearchMatch>, Error> {
+        let hooks = self.thread_hooks()?;
+
+        if hooks.filter && !hooks.plugin.filter(&result)? {
+            return Ok(None);
+        }
+
+        if hooks.transform {
+            return Ok(Some(hooks.plugin.transform(result)?));
+        }
+
+        Ok(Some(result))
+    }
+}
+
+/// Hands every match to a Lua plugin before passing it on to another sink.
+pub struct LuaSink<'a, S> {
+    plugin: &'a LuaPlugin,
+    inner: S,
+}
+
+impl<'a, S: Sink> LuaSink<'a, S> {
+    pub fn new(plugin: &'a LuaPlugin, inner: S) -> Self {
+        Self { plugin, inner }
+    }
+}
+
+impl<S: Sink> Sink for LuaSink<'_, S> {
+    fn begin(&mut self, path: &str) -> Result<(), Error> {
+        self.inner.begin(path)
+    }
+
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        self.plugin.push_result(result)?;
+        self.inner.matched(result)
+    }
+
+    fn context(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        self.inner.context(result)
+    }
+
+    fn context_break(&mut self) -> Result<(), Error> {
+        self.inner.context_break()
+    }
+
+    fn binary_matched(&mut self, path: &str, byte_offset: usize) -> Result<(), Error> {
+        self.inner.binary_matched(path, byte_offset)
+    }
+
+    fn finish(&mut self, path: &str, stats: &Stats) -> Result<(), Error> {
+        self.inner.finish(path, stats)
+    }
 }
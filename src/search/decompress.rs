@@ -0,0 +1,58 @@
+use std::io::{self, BufRead, BufReader};
+
+/// A compression format `--search-zip` can read through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    /// Recognize the format from the magic bytes the stream starts with.
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        const MAGIC: [(&[u8], Compression); 5] = [
+            (&[0x1f, 0x8b], Compression::Gzip),
+            (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], Compression::Xz),
+            (b"BZh", Compression::Bzip2),
+            (&[0x28, 0xb5, 0x2f, 0xfd], Compression::Zstd),
+            (&[0x04, 0x22, 0x4d, 0x18], Compression::Lz4),
+        ];
+
+        MAGIC
+            .iter()
+            .find(|(magic, _)| head.starts_with(magic))
+            .map(|&(_, compression)| compression)
+    }
+}
+
+/// Decompress a reader while it is read, if it is compressed.
+///
+/// The format is recognized from the magic bytes at the start of the stream,
+/// not from the file name, so a `.gz` file holding plain text is still
+/// searched. Input in no known format is passed through as is. Concatenated
+/// members, as left by appending to a `.gz` log, are all read.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let reader: Box<dyn BufRead + 'a> = match Compression::from_magic(reader.fill_buf()?) {
+        Some(Compression::Gzip) => {
+            Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))
+        }
+        Some(Compression::Xz) => Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(reader),
+        )),
+        Some(Compression::Bzip2) => {
+            Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader)))
+        }
+        Some(Compression::Zstd) => Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )),
+        Some(Compression::Lz4) => {
+            Box::new(BufReader::new(lz4_flex::frame::FrameDecoder::new(reader)))
+        }
+        None => Box::new(reader),
+    };
+
+    Ok(reader)
+}
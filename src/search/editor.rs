@@ -0,0 +1,219 @@
+use crate::error::Error;
+use crate::search::matcher::looks_binary;
+use crate::search::result::SearchMatch;
+use crate::search::searcher::Searcher;
+use crate::search::sink::Sink;
+use encoding_rs::{Encoding, UTF_8};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// A file rewritten with `--replace`, before and after the replacement.
+#[derive(Debug)]
+pub struct FileEdit {
+    pub path: String,
+    pub original: Vec<u8>,
+    pub edited: Vec<u8>,
+    pub lines_changed: usize,
+}
+
+/// What happened to a file when editing it in place.
+#[derive(Debug)]
+pub enum EditOutcome {
+    /// Nothing in the file was replaced.
+    Unchanged,
+    /// The file contains NUL bytes and was left alone.
+    Binary(String),
+    /// The file is decoded from another encoding than UTF-8 and was left alone.
+    Transcoded(String),
+    /// The file was rewritten, or would have been with `--dry-run`.
+    Edited(FileEdit),
+}
+
+/// Totals over all edited files.
+#[derive(Debug, Default, Clone)]
+pub struct EditSummary {
+    pub files_changed: usize,
+    pub lines_changed: usize,
+}
+
+impl EditSummary {
+    pub fn add(&mut self, edit: &FileEdit) {
+        self.files_changed += 1;
+        self.lines_changed += edit.lines_changed;
+    }
+}
+
+// Collects the replaced content of every matched line, by line number
+#[derive(Default)]
+struct Replacements {
+    lines: HashMap<usize, Vec<u8>>,
+}
+
+impl Sink for Replacements {
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        if result.matches.iter().any(|span| span.replacement.is_some()) {
+            self.lines
+                .insert(result.line_number, result.replaced_line());
+        }
+        Ok(())
+    }
+}
+
+/// Replace the matches in a file, writing it back unless `--dry-run` is set.
+///
+/// The file is read once and searched from memory, so what gets written is
+/// exactly what was searched.
+pub fn edit_file(searcher: &Searcher, path: &Path) -> Result<EditOutcome, Error> {
+    let file = path.to_string_lossy();
+    let original = fs::read(path).map_err(|err| Error::file(&file, err))?;
+
+    // Replacements are UTF-8, writing them into other encodings would mix the two
+    let bom = Encoding::for_bom(&original);
+    let encoding = bom.map(|(encoding, _)| encoding).or(searcher.encoding());
+    if encoding.is_some_and(|encoding| encoding != UTF_8) {
+        return Ok(EditOutcome::Transcoded(file.into_owned()));
+    }
+
+    // Rewriting lines would corrupt files with NUL bytes
+    if looks_binary(&original) {
+        return Ok(EditOutcome::Binary(file.into_owned()));
+    }
+
+    // The searcher skips a UTF-8 byte order mark, so line up with it
+    let bom_len = bom.map_or(0, |(_, len)| len);
+    let (bom, content) = original.split_at(bom_len);
+
+    let mut replacements = Replacements::default();
+    searcher.search_reader(&file, content, &mut replacements)?;
+    if replacements.lines.is_empty() {
+        return Ok(EditOutcome::Unchanged);
+    }
+
+    let (mut edited, lines_changed) = apply_replacements(content, &replacements.lines);
+    edited.splice(0..0, bom.iter().copied());
+    if lines_changed == 0 {
+        return Ok(EditOutcome::Unchanged);
+    }
+
+    if !searcher.flags().dry_run.is_enabled() {
+        write_atomically(path, &edited).map_err(|source| Error::Write {
+            path: file.to_string(),
+            source,
+        })?;
+    }
+
+    Ok(EditOutcome::Edited(FileEdit {
+        path: file.into_owned(),
+        original,
+        edited,
+        lines_changed,
+    }))
+}
+
+// Rebuild the file line by line, keeping the original line terminators
+fn apply_replacements(original: &[u8], lines: &HashMap<usize, Vec<u8>>) -> (Vec<u8>, usize) {
+    let mut edited = Vec::with_capacity(original.len());
+    let mut lines_changed = 0;
+
+    for (index, line) in original.split_inclusive(|&b| b == b'\n').enumerate() {
+        let Some(replaced) = lines.get(&(index + 1)) else {
+            edited.extend_from_slice(line);
+            continue;
+        };
+
+        let content_len = line.len() - line_terminator_len(line);
+        if line[..content_len] != replaced[..] {
+            lines_changed += 1;
+        }
+        edited.extend_from_slice(replaced);
+        edited.extend_from_slice(&line[content_len..]);
+    }
+
+    (edited, lines_changed)
+}
+
+// Same terminator the searcher strips before matching a line
+fn line_terminator_len(line: &[u8]) -> usize {
+    let content = line.strip_suffix(b"\n").unwrap_or(line);
+    let content = content.strip_suffix(b"\r").unwrap_or(content);
+    line.len() - content.len()
+}
+
+// Write to a temporary file next to the original and rename it over the
+// original, so readers never see a half written file. Symlinks are resolved
+// first, renaming over a link would replace the link instead of its target.
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let path = &fs::canonicalize(path)?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let permissions = fs::metadata(path)?.permissions();
+
+    let mut temp = NamedTempFile::new_in(dir)?;
+    temp.write_all(content)?;
+    temp.as_file().sync_all()?;
+    fs::set_permissions(temp.path(), permissions)?;
+    temp.persist(path).map_err(|err| err.error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::flags::Flags;
+    use crate::search::searcher::SearcherBuilder;
+
+    fn lines(replaced: &[(usize, &str)]) -> HashMap<usize, Vec<u8>> {
+        replaced
+            .iter()
+            .map(|&(line_number, line)| (line_number, line.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_line_terminators() {
+        let (edited, lines_changed) =
+            apply_replacements(b"foo\r\nbar\nfoo\r\n", &lines(&[(1, "baz"), (3, "baz")]));
+        assert_eq!(edited, b"baz\r\nbar\nbaz\r\n");
+        assert_eq!(lines_changed, 2);
+    }
+
+    #[test]
+    fn keeps_missing_final_newline() {
+        let (edited, lines_changed) = apply_replacements(b"bar\nfoo", &lines(&[(2, "baz")]));
+        assert_eq!(edited, b"bar\nbaz");
+        assert_eq!(lines_changed, 1);
+    }
+
+    #[test]
+    fn counts_only_changed_lines() {
+        let (edited, lines_changed) = apply_replacements(b"foo\nbar\n", &lines(&[(1, "foo")]));
+        assert_eq!(edited, b"foo\nbar\n");
+        assert_eq!(lines_changed, 0);
+    }
+
+    #[test]
+    fn keeps_utf8_byte_order_mark() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bom.txt");
+        fs::write(&path, b"\xef\xbb\xbffoo\r\nfoo").unwrap();
+
+        let searcher = SearcherBuilder::new()
+            .pattern("^foo")
+            .flags(Flags {
+                replace: Some("baz".to_string()),
+                ..Flags::default()
+            })
+            .build()
+            .unwrap();
+        let outcome = edit_file(&searcher, &path).unwrap();
+
+        assert!(matches!(outcome, EditOutcome::Edited(edit) if edit.lines_changed == 2));
+        assert_eq!(fs::read(&path).unwrap(), b"\xef\xbb\xbfbaz\r\nbaz");
+    }
+}
@@ -0,0 +1,330 @@
+use encoding_rs::{Decoder, DecoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::io::{self, BufRead};
+
+/// Look up the encoding for a WHATWG label like `utf-16le` or `latin1`.
+pub fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.as_bytes())
+}
+
+/// Reads lines as UTF-8, decoding them from another encoding if needed.
+///
+/// A byte order mark takes precedence over the encoding asked for. Without
+/// either the input is read as is, and UTF-8 input is never transcoded so
+/// invalid sequences reach the matcher untouched.
+///
+/// Lines are split on the newline of the original encoding, so the number of
+/// bytes each line took up in the input is known without encoding it again.
+pub struct LineReader<R> {
+    reader: R,
+    decoder: Option<Decoder>,
+    encoding: Option<&'static Encoding>,
+    // The last line read, in the original encoding, when decoding
+    raw: Vec<u8>,
+    bom_len: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(mut reader: R, encoding: Option<&'static Encoding>) -> io::Result<Self> {
+        let (encoding, bom_len) = match Encoding::for_bom(reader.fill_buf()?) {
+            Some((encoding, bom_len)) => (Some(encoding), bom_len),
+            None => (encoding, 0),
+        };
+        reader.consume(bom_len);
+
+        let encoding = encoding.filter(|&encoding| encoding != UTF_8);
+        Ok(Self {
+            reader,
+            decoder: encoding.map(Encoding::new_decoder_without_bom_handling),
+            encoding,
+            raw: Vec::new(),
+            bom_len,
+        })
+    }
+
+    /// The encoding lines are decoded from, `None` when they are read as is.
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        self.encoding
+    }
+
+    /// Length of the byte order mark skipped at the start.
+    pub fn bom_len(&self) -> usize {
+        self.bom_len
+    }
+
+    /// The start of the input, only available when it is read as is.
+    pub fn head(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.decoder.is_some() {
+            return Ok(None);
+        }
+        self.reader.fill_buf().map(Some)
+    }
+
+    /// The last line read, as it was in the input, when it was decoded.
+    pub fn original_line(&self) -> Option<OriginalLine> {
+        self.encoding.map(|encoding| OriginalLine {
+            encoding,
+            bytes: self.raw.clone(),
+        })
+    }
+
+    /// Read the next line into `line` as UTF-8, terminator included.
+    /// Returns the number of bytes it took up in the input, 0 at the end.
+    pub fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<usize> {
+        let Some(decoder) = &mut self.decoder else {
+            return self.reader.read_until(b'\n', line);
+        };
+
+        self.raw.clear();
+        loop {
+            let bytes_read = self.reader.read_until(b'\n', &mut self.raw)?;
+            if bytes_read == 0 || ends_line(&mut self.reader, &mut self.raw, self.encoding)? {
+                break;
+            }
+        }
+        // The decoder is finished once the last line was decoded
+        if self.raw.is_empty() {
+            return Ok(0);
+        }
+
+        // Flush a character cut short by the end of the input with the last line
+        let last = self.reader.fill_buf()?.is_empty();
+        let max_len = decoder
+            .max_utf8_buffer_length(self.raw.len())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "line too long"))?;
+
+        let start = line.len();
+        line.resize(start + max_len, 0);
+        let (_, _, written, _) = decoder.decode_to_utf8(&self.raw, &mut line[start..], last);
+        line.truncate(start + written);
+
+        Ok(self.raw.len())
+    }
+}
+
+// Whether the line read up to a b'\n' byte ends there. In UTF-16 that byte
+// may be half of another character, or the first half of the newline.
+fn ends_line<R: BufRead>(
+    reader: &mut R,
+    raw: &mut Vec<u8>,
+    encoding: Option<&'static Encoding>,
+) -> io::Result<bool> {
+    // The input ended without a newline
+    if !raw.ends_with(b"\n") {
+        return Ok(true);
+    }
+
+    let newline_at = raw.len() - 1;
+    if encoding == Some(UTF_16LE) {
+        if newline_at % 2 == 1 {
+            return Ok(false);
+        }
+        let Some(&high) = reader.fill_buf()?.first() else {
+            return Ok(true);
+        };
+        reader.consume(1);
+        raw.push(high);
+        Ok(high == 0)
+    } else if encoding == Some(UTF_16BE) {
+        Ok(newline_at % 2 == 1 && raw[newline_at - 1] == 0)
+    } else {
+        // Other encodings never use the newline byte inside a character
+        Ok(true)
+    }
+}
+
+/// A line as it was in the input before it was decoded.
+#[derive(Debug, Clone)]
+pub struct OriginalLine {
+    pub encoding: &'static Encoding,
+    pub bytes: Vec<u8>,
+}
+
+impl OriginalLine {
+    /// Number of input bytes that decoded into the first `decoded_len`
+    /// bytes of the line. Malformed sequences count as the bytes they
+    /// replaced, not as the U+FFFD they became.
+    pub fn original_len(&self, decoded_len: usize) -> usize {
+        let mut decoder = self.encoding.new_decoder_without_bom_handling();
+        let mut output = Vec::new();
+        let mut read = 0;
+        let mut written = 0;
+
+        // Feed the line one byte at a time to see where each character ends
+        while read < self.bytes.len() && written < decoded_len {
+            let input = &self.bytes[read..=read];
+            let (mut result, consumed, len) = decode_step(&mut decoder, input, &mut output);
+            read += consumed;
+            written += len;
+
+            // The decoder reports a malformed sequence once it read past it
+            while let DecoderResult::Malformed(_, read_after) = result {
+                written += REPLACEMENT_CHARACTER.len_utf8();
+                if written >= decoded_len {
+                    return read - usize::from(read_after);
+                }
+                let (next, _, len) = decode_step(&mut decoder, &[], &mut output);
+                result = next;
+                written += len;
+            }
+        }
+
+        read
+    }
+}
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+// Decode without replacing malformed sequences, so the caller sees them
+fn decode_step(
+    decoder: &mut Decoder,
+    input: &[u8],
+    output: &mut Vec<u8>,
+) -> (DecoderResult, usize, usize) {
+    let max_len = decoder
+        .max_utf8_buffer_length_without_replacement(input.len())
+        .unwrap_or(REPLACEMENT_CHARACTER.len_utf8());
+    output.resize(max_len, 0);
+    decoder.decode_to_utf8_without_replacement(input, output, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::SHIFT_JIS;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    // Every decoded line with the number of input bytes it took up
+    fn read_lines(input: &[u8], encoding: Option<&'static Encoding>) -> Vec<(String, usize)> {
+        let mut reader = LineReader::new(input, encoding).unwrap();
+        let mut lines = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            let bytes_read = reader.read_line(&mut line).unwrap();
+            if bytes_read == 0 {
+                return lines;
+            }
+            lines.push((String::from_utf8(line).unwrap(), bytes_read));
+        }
+    }
+
+    #[test]
+    fn utf16le_newline_byte_inside_a_character() {
+        let input = utf16le("a\u{0A01}b\n\u{010A}c\nd\n");
+        assert_eq!(
+            read_lines(&input, Some(UTF_16LE)),
+            [
+                ("a\u{0A01}b\n".to_string(), 8),
+                ("\u{010A}c\n".to_string(), 6),
+                ("d\n".to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn utf16be_newline_byte_inside_a_character() {
+        let input = utf16be("a\u{0A01}b\n\u{010A}c\nd\n");
+        assert_eq!(
+            read_lines(&input, Some(UTF_16BE)),
+            [
+                ("a\u{0A01}b\n".to_string(), 8),
+                ("\u{010A}c\n".to_string(), 6),
+                ("d\n".to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_final_newline() {
+        assert_eq!(
+            read_lines(&utf16le("a\nbc"), Some(UTF_16LE)),
+            [("a\n".to_string(), 4), ("bc".to_string(), 4)]
+        );
+        assert_eq!(
+            read_lines(b"a\nbc", None),
+            [("a\n".to_string(), 2), ("bc".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn odd_trailing_byte_is_replaced() {
+        let mut input = utf16le("a\nb");
+        input.push(b'c');
+        assert_eq!(
+            read_lines(&input, Some(UTF_16LE)),
+            [("a\n".to_string(), 4), ("b\u{FFFD}".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn bom_overrides_encoding_and_counts_towards_offsets() {
+        let mut input = vec![0xff, 0xfe];
+        input.extend(utf16le("ab\ncd\n"));
+
+        let reader = LineReader::new(&input[..], Some(SHIFT_JIS)).unwrap();
+        assert_eq!(reader.encoding(), Some(UTF_16LE));
+        assert_eq!(reader.bom_len(), 2);
+
+        // The second line starts after the BOM and the first line
+        let lines = read_lines(&input, None);
+        assert_eq!(lines[0], ("ab\n".to_string(), 6));
+        assert_eq!(reader.bom_len() + lines[0].1, 8);
+        assert_eq!(&input[8..10], &utf16le("c")[..]);
+    }
+
+    #[test]
+    fn utf8_bom_is_skipped_without_decoding() {
+        let mut reader = LineReader::new(&b"\xef\xbb\xbfab\n"[..], None).unwrap();
+        assert_eq!(reader.encoding(), None);
+        assert_eq!(reader.bom_len(), 3);
+        assert_eq!(reader.head().unwrap(), Some(&b"ab\n"[..]));
+    }
+
+    #[test]
+    fn original_len_over_surrogate_pairs() {
+        let line = OriginalLine {
+            encoding: UTF_16LE,
+            bytes: utf16le("a\u{1F600}b\n"),
+        };
+        // "a" is 1 byte decoded and 2 in the input, the emoji 4 in both
+        assert_eq!(line.original_len(0), 0);
+        assert_eq!(line.original_len(1), 2);
+        assert_eq!(line.original_len(5), 6);
+        assert_eq!(line.original_len(6), 8);
+    }
+
+    #[test]
+    fn original_len_over_malformed_input() {
+        // Each invalid byte became a 3 byte U+FFFD
+        let line = OriginalLine {
+            encoding: SHIFT_JIS,
+            bytes: b"\xfd\xfd foo\n".to_vec(),
+        };
+        let (decoded, _) = SHIFT_JIS.decode_without_bom_handling(&line.bytes);
+        let start = decoded.find("foo").unwrap();
+        assert_eq!(line.original_len(start), 3);
+
+        // A lone surrogate is 2 input bytes, even though the decoder only
+        // notices once it read the character after it
+        let line = OriginalLine {
+            encoding: UTF_16LE,
+            bytes: vec![0x00, 0xd8, b'A', 0x00],
+        };
+        assert_eq!(line.original_len("\u{FFFD}".len()), 2);
+        assert_eq!(line.original_len("\u{FFFD}A".len()), 4);
+
+        // The byte that ended a malformed sequence is decoded again
+        let line = OriginalLine {
+            encoding: encoding_rs::EUC_JP,
+            bytes: b"\x8f\xa1a".to_vec(),
+        };
+        assert_eq!(line.original_len("\u{FFFD}".len()), 2);
+        assert_eq!(line.original_len("\u{FFFD}a".len()), 3);
+    }
+}
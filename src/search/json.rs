@@ -0,0 +1,178 @@
+// src/search/json.rs
+
+//! JSON Lines output for `--json`.
+//!
+//! Every line written to stdout is a single JSON object of the form
+//! `{"type": <event>, "data": {...}}`. The events are:
+//!
+//! - `begin`: a file with matches is about to be reported.
+//!   `{"path": <data>}`
+//! - `match`: a selected line.
+//!   `{"path": <data>, "lines": <data>, "line_number": N, "absolute_offset": N,
+//!   "submatches": [{"match": <data>, "start": N, "end": N, "pattern": N}]}`.
+//!   With `--replace` every submatch also has `"replacement": <data>`.
+//! - `context`: a line around a match, same shape as `match` with no submatches.
+//! - `end`: the file is done.
+//!   `{"path": <data>, "stats": <stats>}`
+//! - `summary`: written once after the whole search.
+//!   `{"stats": <stats>}`
+//!
+//! `<data>` is `{"text": "..."}` when the bytes are valid UTF-8 and
+//! `{"bytes": "..."}` holding standard base64 otherwise, so no content is lost.
+//! `start` and `end` are byte offsets into the line, `absolute_offset` is the
+//! byte offset of the line in the file and `pattern` is the index of the
+//! pattern that produced the submatch.
+//!
+//! Files decoded with `--encoding` or because of a byte order mark are
+//! reported as UTF-8, `start` and `end` index the decoded line while
+//! `absolute_offset` and `bytes_searched` count bytes in the original encoding.
+//!
+//! Matches in files that look binary are not reported as events unless
+//! `--text` is given, they only count towards the stats.
+//!
+//! `<stats>` is `{"files_searched": N, "files_matched": N, "matched_lines": N,
+//! "matches": N, "bytes_searched": N, "elapsed": {"secs": N, "nanos": N}}`.
+
+use crate::error::Error;
+use crate::search::result::{SearchMatch, Stats};
+use crate::search::sink::Sink;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::io::{BufWriter, Stdout, Write as IoWrite};
+
+// Encode raw bytes, falling back to base64 when they are not valid UTF-8
+fn data(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => json!({ "text": text }),
+        Err(_) => json!({ "bytes": base64::engine::general_purpose::STANDARD.encode(bytes) }),
+    }
+}
+
+fn stats_value(stats: &Stats) -> Value {
+    json!({
+        "files_searched": stats.files_searched,
+        "files_matched": stats.files_matched,
+        "matched_lines": stats.matched_lines,
+        "matches": stats.matches,
+        "bytes_searched": stats.bytes_searched,
+        "elapsed": {
+            "secs": stats.elapsed.as_secs(),
+            "nanos": stats.elapsed.subsec_nanos(),
+        },
+    })
+}
+
+fn event(kind: &str, data: Value) -> String {
+    json!({ "type": kind, "data": data }).to_string()
+}
+
+// Build the `match` or `context` event for a single result
+pub fn format_json_match(result: &SearchMatch) -> String {
+    let line = &result.line_content;
+    let submatches: Vec<Value> = result
+        .matches
+        .iter()
+        .map(|span| {
+            let mut submatch = json!({
+                "match": data(&line[span.start..span.end]),
+                "start": span.start,
+                "end": span.end,
+                "pattern": span.pattern,
+            });
+            if let Some(replacement) = &span.replacement {
+                submatch["replacement"] = data(replacement);
+            }
+            submatch
+        })
+        .collect();
+
+    let kind = if result.is_context() {
+        "context"
+    } else {
+        "match"
+    };
+
+    event(
+        kind,
+        json!({
+            "path": data(result.file.as_bytes()),
+            "lines": data(line),
+            "line_number": result.line_number,
+            "absolute_offset": result.byte_offset,
+            "submatches": submatches,
+        }),
+    )
+}
+
+// Prints every search event as a line of JSON, see the module docs
+pub struct JsonPrinter {
+    out: BufWriter<Stdout>,
+    path: Option<String>,
+    begun: bool,
+}
+
+impl JsonPrinter {
+    pub fn new() -> Self {
+        Self {
+            out: BufWriter::new(std::io::stdout()),
+            path: None,
+            begun: false,
+        }
+    }
+
+    fn write_event(&mut self, kind: &str, data: Value) -> Result<(), Error> {
+        writeln!(self.out, "{}", event(kind, data))?;
+        Ok(())
+    }
+
+    // Files without matches print nothing, so `begin` is written lazily
+    fn ensure_begun(&mut self) -> Result<(), Error> {
+        if !self.begun {
+            let path = data(self.path.as_deref().unwrap_or_default().as_bytes());
+            self.write_event("begin", json!({ "path": path }))?;
+            self.begun = true;
+        }
+        Ok(())
+    }
+
+    // Print the final summary of the whole search
+    pub fn summary(&mut self, stats: &Stats) -> Result<(), Error> {
+        self.write_event("summary", json!({ "stats": stats_value(stats) }))?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for JsonPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sink for JsonPrinter {
+    fn begin(&mut self, path: &str) -> Result<(), Error> {
+        self.path = Some(path.to_owned());
+        self.begun = false;
+        Ok(())
+    }
+
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        self.ensure_begun()?;
+        writeln!(self.out, "{}", format_json_match(result))?;
+        Ok(())
+    }
+
+    fn context(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        self.matched(result)
+    }
+
+    fn finish(&mut self, path: &str, stats: &Stats) -> Result<(), Error> {
+        if self.begun {
+            let path = data(path.as_bytes());
+            self.write_event("end", json!({ "path": path, "stats": stats_value(stats) }))?;
+        }
+
+        self.out.flush()?;
+        Ok(())
+    }
+}
@@ -1,33 +1,237 @@
 use crate::app::flags::Flags;
-use crate::search::result::SearchMatch;
+use crate::error::Error;
+use crate::search::decompress::decompress;
+use crate::search::encoding::LineReader;
+use crate::search::result::{MatchSpan, SearchMatch, Stats};
+use crate::search::searcher::Searcher;
+use crate::search::sink::Sink;
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
 use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
+use regex::bytes::{Regex, RegexSet};
+use regex_syntax::ast::{self, Ast, ClassSetItem};
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{BufRead, BufReader};
 use std::sync::Mutex;
-
-use super::printer::{print_count_results, print_match_results};
-
-// TODO: add non-regex search if not needed
+use std::time::Instant;
 
 // Create a regex pattern from the search string, handling case sensitivity
 lazy_static! {
-    static ref REGEX_CACHE: Mutex<HashMap<(String, bool), Regex>> = Mutex::new(HashMap::new());
+    static ref REGEX_CACHE: Mutex<HashMap<(String, RegexOptions), Regex>> =
+        Mutex::new(HashMap::new());
+    // Captures a literal match as group 0 and nothing else, for -r with -F
+    static ref WHOLE_MATCH: Regex = Regex::new(r"(?s-u)\A.*\z").unwrap();
+}
+
+// How a pattern is turned into a regex, part of the cache key
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegexOptions {
+    pub ignore_case: bool,
+    // Ignore case unless the pattern has an uppercase literal, -S
+    pub smart_case: bool,
+    // Only match whole words, -w
+    pub word: bool,
+    // Only match whole lines, -x
+    pub line: bool,
+}
+
+impl RegexOptions {
+    pub fn new(flags: &Flags) -> Self {
+        Self {
+            ignore_case: flags.ignore_case.is_enabled(),
+            smart_case: flags.smart_case.is_enabled(),
+            word: flags.word_regexp.is_enabled(),
+            line: flags.line_regexp.is_enabled(),
+        }
+    }
+}
+
+// The engine used to find matches in a line
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    // One regex per pattern, with a set to quickly find which of them match
+    Regex { set: RegexSet, regexes: Vec<Regex> },
+    Literal(AhoCorasick),
+}
+
+impl Matcher {
+    // Find all non-overlapping matches in a line, leftmost first
+    pub fn find_iter(&self, line: &[u8]) -> Vec<MatchSpan> {
+        match self {
+            Self::Regex { regexes, .. } if regexes.len() == 1 => regexes[0]
+                .find_iter(line)
+                .map(|m| MatchSpan::new(m.start(), m.end(), 0))
+                .collect(),
+            Self::Regex { set, regexes } => {
+                let mut spans: Vec<MatchSpan> = set
+                    .matches(line)
+                    .into_iter()
+                    .flat_map(|pattern| {
+                        regexes[pattern]
+                            .find_iter(line)
+                            .map(move |m| MatchSpan::new(m.start(), m.end(), pattern))
+                    })
+                    .collect();
+
+                // Earlier patterns win when two matches start at the same position
+                spans.sort_by_key(|span| (span.start, span.pattern));
+                let mut merged: Vec<MatchSpan> = Vec::with_capacity(spans.len());
+                for span in spans {
+                    let overlaps = merged
+                        .last()
+                        .is_some_and(|last| span.start < last.end || span.start == last.start);
+                    if !overlaps {
+                        merged.push(span);
+                    }
+                }
+                merged
+            }
+            Self::Literal(ac) => ac
+                .find_iter(line)
+                .map(|m| MatchSpan::new(m.start(), m.end(), m.pattern().as_usize()))
+                .collect(),
+        }
+    }
+}
+
+impl Matcher {
+    // Fill in the replacement of every span, expanding $1 and $name from the
+    // regex captures. Literal matches only have group 0, so other groups
+    // expand to nothing like groups a regex did not capture.
+    pub fn replace(&self, line: &[u8], spans: &mut [MatchSpan], replacement: &str) {
+        let replacement = &brace_group_numbers(replacement.as_bytes());
+        for span in spans {
+            let mut expanded = Vec::new();
+            let captures = match self {
+                Self::Regex { regexes, .. } => regexes
+                    .get(span.pattern)
+                    .and_then(|regex| regex.captures_at(line, span.start))
+                    .filter(|caps| caps.get(0).is_some_and(|m| m.end() == span.end)),
+                Self::Literal(_) => WHOLE_MATCH.captures(&line[span.start..span.end]),
+            };
+
+            match captures {
+                Some(captures) => captures.expand(replacement, &mut expanded),
+                None => expanded.extend_from_slice(replacement),
+            }
+            span.replacement = Some(expanded);
+        }
+    }
+}
+
+// The regex crate reads `$1_new` as a group named `1_new`, which cannot
+// exist since names never start with a digit. Rewrite it to `${1}_new` so it
+// expands group 1 followed by `_new`, like sed does.
+fn brace_group_numbers(replacement: &[u8]) -> Vec<u8> {
+    let is_name_byte = |byte: u8| byte == b'_' || byte.is_ascii_alphanumeric();
+    let mut output = Vec::with_capacity(replacement.len());
+    let mut i = 0;
+
+    while i < replacement.len() {
+        match &replacement[i..] {
+            // An escaped dollar sign
+            [b'$', b'$', ..] => {
+                output.extend_from_slice(b"$$");
+                i += 2;
+            }
+            [b'$', rest @ ..] if rest.first().is_some_and(u8::is_ascii_digit) => {
+                let digits = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+                let number = &rest[..digits];
+                if rest.get(digits).is_some_and(|&byte| is_name_byte(byte)) {
+                    output.extend_from_slice(b"${");
+                    output.extend_from_slice(number);
+                    output.push(b'}');
+                } else {
+                    output.push(b'$');
+                    output.extend_from_slice(number);
+                }
+                i += 1 + digits;
+            }
+            [byte, ..] => {
+                output.push(*byte);
+                i += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+
+    output
+}
+
+// Build the matcher for the needles according to the search flags
+pub fn compile_matcher(needles: &[String], flags: &Flags) -> Result<Matcher, Error> {
+    let options = RegexOptions::new(flags);
+
+    // Literals have no escapes, so any uppercase character counts for -S
+    let has_uppercase = |needle: &String| needle.chars().any(char::is_uppercase);
+    let ignore_case =
+        options.ignore_case || (options.smart_case && !needles.iter().any(has_uppercase));
+    let mixed_case = options.smart_case
+        && !options.ignore_case
+        && needles.iter().any(has_uppercase)
+        && !needles.iter().all(has_uppercase);
+
+    // Aho-Corasick only folds ASCII case, uses one case setting for all
+    // needles and knows nothing about word or line boundaries, so let the
+    // regex engine handle those literals
+    let needs_regex = (ignore_case && needles.iter().any(|needle| !needle.is_ascii()))
+        || mixed_case
+        || options.word
+        || options.line;
+
+    if !flags.fixed_strings.is_enabled() {
+        compile_regex_matcher(needles, options)
+    } else if needs_regex {
+        let escaped: Vec<String> = needles.iter().map(|needle| regex::escape(needle)).collect();
+        compile_regex_matcher(&escaped, options)
+    } else {
+        compile_literal_matcher(needles, ignore_case)
+    }
+}
+
+fn compile_regex_matcher(needles: &[String], options: RegexOptions) -> Result<Matcher, Error> {
+    let regexes = needles
+        .iter()
+        .map(|needle| compile_or_get_regex(needle, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let set = RegexSet::new(regexes.iter().map(Regex::as_str))?;
+
+    Ok(Matcher::Regex { set, regexes })
+}
+
+fn compile_literal_matcher(needles: &[String], ignore_case: bool) -> Result<Matcher, Error> {
+    let literal = AhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .ascii_case_insensitive(ignore_case)
+        .build(needles)?;
+
+    Ok(Matcher::Literal(literal))
 }
 
-pub fn compile_or_get_regex(needle: &str, ignore_case: bool) -> Result<Regex, regex::Error> {
-    let key = (needle.to_string(), ignore_case);
+pub fn compile_or_get_regex(needle: &str, options: RegexOptions) -> Result<Regex, regex::Error> {
+    let key = (needle.to_string(), options);
     let mut cache = REGEX_CACHE.lock().unwrap();
 
     if let Some(regex) = cache.get(&key) {
         return Ok(regex.clone());
     }
 
+    // The group keeps alternations inside the boundaries and the capture
+    // group numbers unchanged
+    let needle = if options.line {
+        format!("^(?:{needle})$")
+    } else if options.word {
+        format!(r"\b(?:{needle})\b")
+    } else {
+        needle.to_owned()
+    };
+
+    let ignore_case =
+        options.ignore_case || (options.smart_case && !has_uppercase_literal(&needle));
     let needle = if ignore_case {
         format!("(?i){needle}")
     } else {
-        needle.to_owned()
+        needle
     };
 
     let regex = Regex::new(&needle)?;
@@ -35,97 +239,410 @@ pub fn compile_or_get_regex(needle: &str, ignore_case: bool) -> Result<Regex, re
     Ok(regex)
 }
 
+// Whether a pattern has an uppercase letter among its literal characters.
+// Escapes like \W and classes like [[:upper:]] do not count, patterns that
+// do not parse are left for the regex compiler to report.
+fn has_uppercase_literal(needle: &str) -> bool {
+    match ast::parse::Parser::new().parse(needle) {
+        Ok(ast) => ast::visit(&ast, UppercaseLiterals(false)).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+struct UppercaseLiterals(bool);
+
+impl ast::Visitor for UppercaseLiterals {
+    type Output = bool;
+    type Err = ();
+
+    fn finish(self) -> Result<bool, ()> {
+        Ok(self.0)
+    }
+
+    fn visit_pre(&mut self, ast: &Ast) -> Result<(), ()> {
+        if let Ast::Literal(literal) = ast {
+            self.0 |= literal.c.is_uppercase();
+        }
+        Ok(())
+    }
+
+    fn visit_class_set_item_pre(&mut self, item: &ClassSetItem) -> Result<(), ()> {
+        match item {
+            ClassSetItem::Literal(literal) => self.0 |= literal.c.is_uppercase(),
+            ClassSetItem::Range(range) => {
+                self.0 |= range.start.c.is_uppercase() || range.end.c.is_uppercase();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// What to do once a NUL byte shows that a file is binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryMode {
+    /// Report the first match with `Sink::binary_matched` instead of
+    /// printing the lines, and stop searching the file.
+    Report,
+    /// Like `Report`, but search the rest of the file so the stats count
+    /// every match.
+    Search,
+    /// Search the file as if it were text.
+    Text,
+}
+
 // Search for matches in a specific file
-pub fn search_file_for_patterns(
-    needle: &str,
+pub fn search_file_for_patterns<S: Sink>(
+    searcher: &Searcher,
     file: &str,
-    flags: &Flags,
-) -> Result<bool, io::Error> {
+    binary: BinaryMode,
+    sink: &mut S,
+) -> Result<Stats, Error> {
     // Open the file for reading
-    let file_handle = File::open(file)?;
+    let file_handle = File::open(file).map_err(|err| Error::file(file, err))?;
     let reader = BufReader::new(file_handle);
 
-    // Compile the regex pattern
-    let regex = compile_or_get_regex(needle, flags.ignore_case.is_enabled()).unwrap();
+    if searcher.flags().search_zip.is_enabled() {
+        let reader = decompress(reader).map_err(|err| Error::file(file, err))?;
+        return search_reader_for_patterns(searcher, file, reader, binary, sink);
+    }
+    search_reader_for_patterns(searcher, file, reader, binary, sink)
+}
+
+// Search for matches in any buffered reader, `file` is the name reported for it
+pub fn search_reader_for_patterns<R: BufRead, S: Sink>(
+    searcher: &Searcher,
+    file: &str,
+    reader: R,
+    binary: BinaryMode,
+    sink: &mut S,
+) -> Result<Stats, Error> {
+    let started = Instant::now();
+    let (matcher, flags) = (searcher.matcher(), searcher.flags());
+
+    // Decode to UTF-8 before sniffing, UTF-16 text is full of NUL bytes
+    let mut reader =
+        LineReader::new(reader, searcher.encoding()).map_err(|err| Error::file(file, err))?;
+
+    // Offset of the first NUL byte once the file turned out to be binary,
+    // sniffed from the first block and then from every line. Decoded input
+    // is only sniffed line by line.
+    let mut binary_offset = None;
+    let mut binary_reported = false;
+    if binary != BinaryMode::Text {
+        let head = reader.head().map_err(|err| Error::file(file, err))?;
+        binary_offset = head.and_then(find_nul);
+    }
+
+    sink.begin(file)?;
 
-    let mut results: Vec<SearchMatch> = Vec::new();
-    // Lock stdout to prevent interleaved output
-    let stdout = std::io::stdout();
-    let _ = stdout.lock();
-    let search_once = flags.list_files.is_enabled();
+    let mut stats = Stats {
+        files_searched: 1,
+        bytes_searched: reader.bom_len(),
+        ..Stats::default()
+    };
+    let search_once = flags.search_once();
+    // Set once -m is reached, only trailing context is read after that
+    let mut limit_reached = flags.max_count == Some(0);
+    let mut context = ContextWindow::new(flags);
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
     // Iterate through each line in the file
-    for (line_number, line) in reader.lines().enumerate() {
+    loop {
+        // Other workers may have settled the search, see Searcher::is_done
+        if searcher.is_done() || (limit_reached && !context.in_after()) {
+            break;
+        }
+
+        buffer.clear();
+        // Offsets are counted in bytes of the original encoding
+        let bytes_read = reader
+            .read_line(&mut buffer)
+            .map_err(|err| Error::file(file, err))?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        let line_offset = stats.bytes_searched;
+        stats.bytes_searched += bytes_read;
+        line_number += 1;
+
+        if binary != BinaryMode::Text && binary_offset.is_none() {
+            binary_offset = find_nul(&buffer).map(|pos| match reader.original_line() {
+                Some(original) => line_offset + original.original_len(pos),
+                None => line_offset + pos,
+            });
+        }
+
+        // Lines are matched as raw bytes, invalid UTF-8 included
+        let line = trim_line_terminator(&buffer);
+
+        if limit_reached {
+            // The lines of a binary file are not printed, context included
+            if binary_offset.is_some() {
+                break;
+            }
+            context.push(file, line_number, line_offset, line, sink)?;
+            continue;
+        }
+
         // Process each line to find matches
-        let line = process_line(
+        let result = process_line(
             file,
-            line_number,
+            line_number - 1,
+            line_offset,
             line,
-            &regex,
+            matcher,
             flags.invert_match.is_enabled(),
-        )?;
+        );
 
-        // Add the match to the results if it exists
-        if let Some(result) = line {
-            results.push(result);
+        // Let the Lua hooks drop or rewrite the match
+        let mut result = match (result, searcher.lua_hooks()) {
+            (Some(result), Some(hooks)) => hooks.apply(result)?,
+            (result, _) => result,
+        };
+
+        // Keep the line as it was in the file to map -b offsets within it
+        if let (Some(result), true) = (&mut result, flags.byte_offset.is_enabled()) {
+            result.original = reader.original_line();
+        }
+
+        if let (Some(result), Some(replacement)) = (&mut result, &flags.replace) {
+            matcher.replace(&result.line_content, &mut result.matches, replacement);
+        }
+
+        // Report the match if it exists
+        if let Some(result) = result {
+            // Another worker may have used up --max-total in the meantime
+            if !searcher.reserve_match() {
+                break;
+            }
+
+            stats.matched_lines += 1;
+            stats.matches += result.matches.len();
+
+            if let Some(offset) = binary_offset {
+                if !binary_reported {
+                    sink.binary_matched(file, offset)?;
+                    binary_reported = true;
+                }
+                // Only counting and --binary look at the rest of a binary file
+                if !flags.count.is_enabled() && binary != BinaryMode::Search {
+                    break;
+                }
+            } else {
+                context.flush_before(file, sink)?;
+                context.emit(&result, sink)?;
+                context.start_after();
+            }
 
             if search_once {
                 break;
             }
+            limit_reached = flags.max_count == Some(stats.matched_lines);
+        } else if binary_offset.is_none() {
+            context.push(file, line_number, line_offset, line, sink)?;
         }
     }
 
-    if !results.is_empty() && !flags.count.is_enabled() {
-        print_match_results(&results, flags);
-    } else if flags.count.is_enabled() {
-        print_count_results(&results);
-    }
+    stats.files_matched = usize::from(stats.matched_lines > 0);
+    stats.elapsed = started.elapsed();
+    sink.finish(file, &stats)?;
+
+    Ok(stats)
+}
+
+// Text files do not contain NUL bytes, binary files almost always do
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    find_nul(bytes).is_some()
+}
 
-    Ok(!results.is_empty())
+fn find_nul(bytes: &[u8]) -> Option<usize> {
+    bytes.iter().position(|&byte| byte == 0)
+}
+
+// Strip the trailing "\n" or "\r\n" from a line, like `BufRead::lines` does
+fn trim_line_terminator(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
 }
 
 pub fn process_line(
     file: &str,
     line_number: usize,
-    line: io::Result<String>,
-    regex: &Regex,
+    byte_offset: usize,
+    line_content: &[u8],
+    matcher: &Matcher,
     invert_match: bool,
-) -> Result<Option<SearchMatch>, io::Error> {
-    // Handle potential invalid UTF-8 sequences gracefully
-    let line_content = match line {
-        Ok(content) => content,
-        Err(_) => return Ok(None), // Skip lines with invalid UTF-8
-    };
-
-    // Find matches in the line content using the regex
-    let matches: Vec<_> = regex.find_iter(&line_content).collect();
+) -> Option<SearchMatch> {
+    // Find matches in the line content
+    let matches = matcher.find_iter(line_content);
 
     if invert_match {
         if matches.is_empty() {
             // Line does NOT match the regex; it's a match for inverted search
-            Ok(Some(SearchMatch::new(
+            Some(SearchMatch::new(
                 file,
                 line_number + 1, // Line numbers are 1-based
-                line_content,
+                byte_offset,
+                line_content.to_vec(),
                 Vec::new(), // No matches since we're inverting
-            )))
+            ))
         } else {
             // Line matches the regex; skip it in inverted search
-            Ok(None)
+            None
         }
     } else if !matches.is_empty() {
         // Line matches the regex; include it in the results
-        let match_positions = matches
-            .iter()
-            .map(|m| (m.start(), m.end()))
-            .collect::<Vec<(usize, usize)>>();
-        Ok(Some(SearchMatch::new(
+        Some(SearchMatch::new(
             file,
             line_number + 1, // Line numbers are 1-based
-            line_content,
-            match_positions,
-        )))
+            byte_offset,
+            line_content.to_vec(),
+            matches,
+        ))
     } else {
         // Line does not match the regex; skip it
-        Ok(None)
+        None
+    }
+}
+
+// Tracks the lines surrounding matches for -A/-B/-C output
+struct ContextWindow {
+    before: VecDeque<(usize, usize, Vec<u8>)>,
+    before_len: usize,
+    after_len: usize,
+    after_remaining: usize,
+    enabled: bool,
+    last_line: Option<usize>,
+}
+
+impl ContextWindow {
+    fn new(flags: &Flags) -> Self {
+        let enabled = flags.has_context();
+        let (before_len, after_len) = if enabled {
+            (flags.before_context, flags.after_context)
+        } else {
+            (0, 0)
+        };
+
+        Self {
+            before: VecDeque::with_capacity(before_len),
+            before_len,
+            after_len,
+            after_remaining: 0,
+            enabled,
+            last_line: None,
+        }
+    }
+
+    // Report a line, separating it from the previous one if they are not adjacent
+    fn emit<S: Sink>(&mut self, result: &SearchMatch, sink: &mut S) -> Result<(), Error> {
+        let gap = self
+            .last_line
+            .is_some_and(|last| result.line_number > last + 1);
+        if self.enabled && gap {
+            sink.context_break()?;
+        }
+        self.last_line = Some(result.line_number);
+
+        if result.is_context() {
+            sink.context(result)
+        } else {
+            sink.matched(result)
+        }
+    }
+
+    // Emit the buffered lines leading up to a match
+    fn flush_before<S: Sink>(&mut self, file: &str, sink: &mut S) -> Result<(), Error> {
+        while let Some((line_number, byte_offset, line)) = self.before.pop_front() {
+            let result = SearchMatch::context(file, line_number, byte_offset, line);
+            self.emit(&result, sink)?;
+        }
+
+        Ok(())
+    }
+
+    fn in_after(&self) -> bool {
+        self.after_remaining > 0
+    }
+
+    fn start_after(&mut self) {
+        self.after_remaining = self.after_len;
+    }
+
+    // Handle a non-matching line: either emit it as trailing context or
+    // remember it in case a match follows shortly
+    fn push<S: Sink>(
+        &mut self,
+        file: &str,
+        line_number: usize,
+        byte_offset: usize,
+        line: &[u8],
+        sink: &mut S,
+    ) -> Result<(), Error> {
+        if self.after_remaining > 0 {
+            self.after_remaining -= 1;
+            let result = SearchMatch::context(file, line_number, byte_offset, line.to_vec());
+            return self.emit(&result, sink);
+        }
+
+        if self.before_len > 0 {
+            if self.before.len() == self.before_len {
+                self.before.pop_front();
+            }
+            self.before
+                .push_back((line_number, byte_offset, line.to_vec()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::flags::OptionState;
+
+    // The line with every match of `pattern` replaced, like -r prints it
+    fn replace(pattern: &str, fixed_strings: bool, line: &str, replacement: &str) -> String {
+        let mut flags = Flags::default();
+        if fixed_strings {
+            flags.fixed_strings = OptionState::Enabled;
+        }
+        let matcher = compile_matcher(&[pattern.to_string()], &flags).unwrap();
+
+        let line = line.as_bytes().to_vec();
+        let mut spans = matcher.find_iter(&line);
+        matcher.replace(&line, &mut spans, replacement);
+        let result = SearchMatch::new("test", 0, 0, line, spans);
+        String::from_utf8(result.replaced_line()).unwrap()
+    }
+
+    #[test]
+    fn brace_group_numbers_before_name_bytes() {
+        assert_eq!(brace_group_numbers(b"$1_new"), b"${1}_new");
+        assert_eq!(brace_group_numbers(b"$12a"), b"${12}a");
+        assert_eq!(brace_group_numbers(b"$1-new"), b"$1-new");
+        assert_eq!(brace_group_numbers(b"$$1_new"), b"$$1_new");
+        assert_eq!(brace_group_numbers(b"${1}_new $name"), b"${1}_new $name");
+    }
+
+    #[test]
+    fn replace_expands_groups() {
+        assert_eq!(replace("(fo)o", false, "a foo b", "$1_new"), "a fo_new b");
+        assert_eq!(replace("(fo)o", false, "a foo b", "c$$"), "a c$ b");
+        assert_eq!(replace("(fo)o", false, "a foo b", "$$1"), "a $1 b");
+        assert_eq!(
+            replace("(?P<name>fo)o", false, "a foo b", "${name}_x $name"),
+            "a fo_x fo b"
+        );
+    }
+
+    #[test]
+    fn replace_fixed_strings_like_regexes() {
+        assert_eq!(replace("foo", true, "a foo b", "$1_x"), "a _x b");
+        assert_eq!(replace("foo", true, "a foo b", "c$$"), "a c$ b");
+        assert_eq!(replace("foo", true, "a foo b", "[$0]"), "a [foo] b");
     }
 }
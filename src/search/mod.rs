@@ -1,4 +1,9 @@
+pub mod decompress;
+pub mod editor;
+pub mod encoding;
+pub mod json;
 pub mod matcher;
 pub mod printer;
 pub mod result;
 pub mod searcher;
+pub mod sink;
@@ -1,57 +1,200 @@
 // src/search/printer.rs
 
 use crate::app::flags::Flags;
-use crate::search::result::SearchMatch;
+use crate::error::Error;
+use crate::search::editor::{EditSummary, FileEdit};
+use crate::search::result::{MatchSpan, SearchMatch, Stats};
+use crate::search::sink::Sink;
 use colored::Colorize;
-use std::collections::HashMap;
-use std::io::{BufWriter, Write as IoWrite};
+use ignore::types::FileTypeDef;
+use similar::TextDiff;
+use std::io::{BufWriter, Stdout, Write as IoWrite};
 
-// Print the count of matches per file
-pub fn print_count_results(results: &[SearchMatch]) {
-    // Create a HashMap to store counts of matches per file
-    let mut file_counts: HashMap<String, usize> = HashMap::new();
+// Prints results as colored text, one file after another
+pub struct Printer {
+    flags: Flags,
+    out: BufWriter<Stdout>,
+    header_printed: bool,
+}
+
+impl Printer {
+    pub fn new(flags: &Flags) -> Self {
+        Self {
+            flags: flags.clone(),
+            out: BufWriter::new(std::io::stdout()),
+            header_printed: false,
+        }
+    }
 
-    // Iterate through each match and increment the count for the file
-    for result in results {
-        let count = file_counts.entry(result.file.clone()).or_insert(0);
-        *count += 1;
+    // Print the file name above the first line reported for a file
+    fn print_header(&mut self, file: &str) -> Result<(), Error> {
+        if !self.header_printed && !self.flags.no_file_names.is_enabled() {
+            writeln!(self.out, "{}", file.bright_blue())?;
+        }
+        self.header_printed = true;
+        Ok(())
     }
 
-    for (file, count) in file_counts.iter() {
-        let output = format_count_result(file, *count);
-        let stdout = std::io::stdout();
-        let mut handle = BufWriter::new(stdout.lock());
-        writeln!(handle, "{}", output).expect("Failed to write to stdout");
+    // Only the file names are printed when listing files with matches
+    fn lines_hidden(&self) -> bool {
+        self.flags.count.is_enabled()
+            || (self.flags.list_files.is_enabled() && !self.flags.no_file_names.is_enabled())
     }
 }
 
-// Print detailed match results
-pub fn print_match_results(results: &[SearchMatch], flags: &Flags) {
-    if results.is_empty() {
-        return;
+impl Sink for Printer {
+    fn begin(&mut self, _path: &str) -> Result<(), Error> {
+        self.header_printed = false;
+        Ok(())
     }
 
-    if !flags.no_file_names.is_enabled() {
-        // All results share the same file
-        let file_name = results[0].file.clone();
-        println!("{}", file_name.bright_blue());
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        if self.flags.count.is_enabled() || self.flags.files_without_match.is_enabled() {
+            return Ok(());
+        }
+
+        // Every match carries its own path, there are no headers
+        if self.flags.vimgrep.is_enabled() {
+            for output in format_vimgrep_result(result) {
+                writeln!(self.out, "{}", output)?;
+            }
+            return Ok(());
+        }
+
+        // Lines selected by -v have no matches to print on their own
+        let nothing_to_print = self.flags.only_matching.is_enabled() && result.matches.is_empty();
+        if nothing_to_print && !self.lines_hidden() {
+            return Ok(());
+        }
+
+        self.print_header(&result.file)?;
+        if self.lines_hidden() {
+            return Ok(());
+        }
+
+        if self.flags.only_matching.is_enabled() {
+            for output in format_only_matching(result, &self.flags) {
+                writeln!(self.out, "{}", output)?;
+            }
+            return Ok(());
+        }
+
+        let output = format_match_result(result, &self.flags);
+        writeln!(self.out, "{}", output)?;
+        Ok(())
+    }
 
-        if flags.list_files.is_enabled() {
-            return;
+    fn context(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        // There is nothing to extract from a context line
+        if self.flags.only_matching.is_enabled() {
+            return Ok(());
+        }
+        self.matched(result)
+    }
+
+    fn context_break(&mut self) -> Result<(), Error> {
+        if !self.lines_hidden() && !self.flags.only_matching.is_enabled() {
+            writeln!(self.out, "{}", "--".bright_black())?;
+        }
+        Ok(())
+    }
+
+    fn binary_matched(&mut self, path: &str, byte_offset: usize) -> Result<(), Error> {
+        if self.flags.count.is_enabled() || self.flags.files_without_match.is_enabled() {
+            return Ok(());
+        }
+
+        if self.flags.vimgrep.is_enabled() {
+            let note = format_binary_match(byte_offset);
+            writeln!(self.out, "{}:{note}", path.bright_blue())?;
+            return Ok(());
+        }
+
+        self.print_header(path)?;
+        if self.lines_hidden() {
+            return Ok(());
+        }
+
+        writeln!(self.out, "{}", format_binary_match(byte_offset))?;
+        Ok(())
+    }
+
+    fn finish(&mut self, path: &str, stats: &Stats) -> Result<(), Error> {
+        if self.flags.count.is_enabled() && stats.has_match() {
+            writeln!(
+                self.out,
+                "{}",
+                format_count_result(path, stats.matched_lines)
+            )?;
+        }
+        if self.flags.files_without_match.is_enabled() && !stats.has_match() {
+            writeln!(self.out, "{}", path.bright_blue())?;
         }
+
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+// Print the known file types for --type-list, one per line
+pub fn print_type_list(definitions: &[FileTypeDef]) -> Result<(), Error> {
+    let stdout = std::io::stdout();
+    let mut handle = BufWriter::new(stdout.lock());
+
+    for def in definitions {
+        writeln!(
+            handle,
+            "{}: {}",
+            def.name().bright_blue(),
+            def.globs().join(", ")
+        )?;
     }
 
-    results.iter().for_each(|res| {
-        let output = format_match_result(res, flags);
-        let stdout = std::io::stdout();
-        let mut handle = BufWriter::new(stdout.lock());
-        writeln!(handle, "{}", output).expect("Failed to write to stdout");
-    });
+    handle.flush()?;
+    Ok(())
 }
 
-// Sanitize output to prevent control characters from affecting the terminal
-fn sanitize_output(output: &str) -> String {
+// Format the changes --dry-run would make to a file as a colored unified diff
+pub fn format_diff(edit: &FileEdit) -> String {
+    let original = String::from_utf8_lossy(&edit.original);
+    let edited = String::from_utf8_lossy(&edit.edited);
+    let diff = TextDiff::from_lines(original.as_ref(), edited.as_ref())
+        .unified_diff()
+        .header(&edit.path, &edit.path)
+        .to_string();
+
+    let mut output = String::new();
+    for line in diff.lines() {
+        let line = sanitize_output(line.as_bytes());
+        let colored = if line.starts_with("---") || line.starts_with("+++") {
+            line.bold()
+        } else if line.starts_with("@@") {
+            line.cyan()
+        } else if line.starts_with('-') {
+            line.red()
+        } else if line.starts_with('+') {
+            line.green()
+        } else {
+            line.normal()
+        };
+        output.push_str(&format!("{colored}\n"));
+    }
     output
+}
+
+// Format the totals printed after editing files
+pub fn format_edit_summary(summary: &EditSummary, dry_run: bool) -> String {
+    let verb = if dry_run { "would change" } else { "changed" };
+    format!(
+        "{verb} {} lines in {} files",
+        summary.lines_changed, summary.files_changed
+    )
+}
+
+// Sanitize output to prevent control characters from affecting the terminal,
+// invalid UTF-8 is shown lossily
+fn sanitize_output(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
         .chars()
         .filter(|c| !c.is_control())
         .collect::<String>()
@@ -59,46 +202,126 @@ fn sanitize_output(output: &str) -> String {
 
 // Format a match result for printing
 pub fn format_match_result(result: &SearchMatch, flags: &Flags) -> String {
+    // Context lines use '-' instead of ':' like grep does
+    let separator = if result.is_context() { '-' } else { ':' };
+    let first_match = result.matches.first().map(|span| span.start);
+    let mut output = format_position(result, first_match, result.byte_offset, separator, flags);
+
+    // Context lines have nothing to highlight, dim them instead
+    if result.is_context() {
+        let sanitized_line_content = sanitize_output(&result.line_content);
+        output.push_str(&sanitized_line_content.dimmed().to_string());
+        return output;
+    }
+
+    // Highlight the matches in the line content, sanitizing it piece by piece
+    // so the match positions still line up
+    output.push_str(&highlight_matches(&result.line_content, &result.matches));
+    output
+}
+
+// Format a line once per match as path:line:column:text, the format quickfix
+// lists in Vim, Emacs and VS Code understand
+pub fn format_vimgrep_result(result: &SearchMatch) -> Vec<String> {
+    let line = highlight_matches(&result.line_content, &result.matches);
+
+    // Lines selected by -v have no matches, point at their start instead
+    let mut starts: Vec<usize> = result.matches.iter().map(|span| span.start).collect();
+    if starts.is_empty() {
+        starts.push(0);
+    }
+
+    starts
+        .into_iter()
+        .map(|start| {
+            format!(
+                "{}:{}:{}:{line}",
+                result.file.bright_blue(),
+                result.line_number,
+                result.column(start)
+            )
+        })
+        .collect()
+}
+
+// Format the line number, column and byte offset in front of a line, each
+// followed by the separator. There is no column without a match.
+fn format_position(
+    result: &SearchMatch,
+    match_start: Option<usize>,
+    byte_offset: usize,
+    separator: char,
+    flags: &Flags,
+) -> String {
     let mut output = String::new();
 
-    // Include the line number if the flag is setw
     if !flags.no_file_lines.is_enabled() {
-        output.push_str(&format!("{}:", result.line_number));
+        output.push_str(&format!("{}{separator}", result.line_number));
+    }
+    if flags.column.is_enabled() {
+        if let Some(start) = match_start {
+            output.push_str(&format!("{}{separator}", result.column(start)));
+        }
+    }
+    if flags.byte_offset.is_enabled() {
+        output.push_str(&format!("{byte_offset}{separator}"));
     }
 
-    // Sanitize the line content
-    let sanitized_line_content = sanitize_output(result.line_content.as_str());
-    // Highlight the matches in the line content
-    output.push_str(&highlight_matches(&sanitized_line_content, &result.matches));
     output
 }
 
+// Format every match in a line on its own, or what replaces it with --replace
+pub fn format_only_matching(result: &SearchMatch, flags: &Flags) -> Vec<String> {
+    result
+        .matches
+        .iter()
+        .map(|span| {
+            let byte_offset = result.file_offset(span.start);
+            let mut output = format_position(result, Some(span.start), byte_offset, ':', flags);
+
+            let text = span
+                .replacement
+                .as_deref()
+                .unwrap_or(&result.line_content[span.start..span.end]);
+            output.push_str(&sanitize_output(text).red().to_string());
+            output
+        })
+        .collect()
+}
+
+// Format the note printed instead of the lines of a matching binary file
+pub fn format_binary_match(byte_offset: usize) -> String {
+    format!("binary file matches (found NUL byte around offset {byte_offset})")
+        .dimmed()
+        .to_string()
+}
+
 // Format the count result for printing
 pub fn format_count_result(file: &str, count: usize) -> String {
     format!("{}:{}", file.bright_blue(), count)
 }
 
 // Highlight matches in a line by coloring matched text in red
-pub fn highlight_matches(line: &str, matches: &[(usize, usize)]) -> String {
+pub fn highlight_matches(line: &[u8], matches: &[MatchSpan]) -> String {
     let mut output = String::new();
     let mut last_end = 0;
 
-    for (start, end) in matches {
+    for span in matches {
         // Ensure the indices are within bounds
-        let start = (*start).min(line.len());
-        let end = (*end).min(line.len());
+        let start = span.start.min(line.len());
+        let end = span.end.min(line.len());
 
         // Append the text before the match
-        output.push_str(&line[last_end..start]);
+        output.push_str(&sanitize_output(&line[last_end..start]));
 
-        // Append the matched text in red
-        let matched_text = &line[start..end];
-        output.push_str(&matched_text.red().to_string());
+        // Append the matched text, or what replaces it, in red
+        let matched_text = span.replacement.as_deref().unwrap_or(&line[start..end]);
+        output.push_str(&sanitize_output(matched_text).red().to_string());
 
         last_end = end;
     }
 
     // Append the text after the last match
-    output.push_str(&line[last_end..]);
+    output.push_str(&sanitize_output(&line[last_end..]));
     output
 }
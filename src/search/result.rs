@@ -1,10 +1,40 @@
+use crate::search::encoding::OriginalLine;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    Match,   // Line selected by the search
+    Context, // Line surrounding a selected line
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchSpan {
+    pub start: usize,                 // Byte offset where the match starts in the line
+    pub end: usize,                   // Byte offset where the match ends in the line
+    pub pattern: usize,               // Index of the pattern that produced the match
+    pub replacement: Option<Vec<u8>>, // Bytes replacing the match when using --replace
+}
+
+impl MatchSpan {
+    pub fn new(start: usize, end: usize, pattern: usize) -> Self {
+        Self {
+            start,
+            end,
+            pattern,
+            replacement: None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct SearchMatch {
-    pub file: String,                 // Name of the file containing the line
-    pub line_number: usize,           // Line number in the file
-    pub line_content: String,         // Content of the line
-    pub matches: Vec<(usize, usize)>, // (start, end) positions of matches           // Indicates if this is an inverted match
+    pub file: String,                   // Name of the file containing the line
+    pub line_number: usize,             // Line number in the file
+    pub byte_offset: usize,             // Offset of the start of the line in the file
+    pub line_content: Vec<u8>,          // Raw bytes of the line, without its terminator
+    pub matches: Vec<MatchSpan>,        // Positions of matches in the line
+    pub kind: MatchKind,                // Whether this is a match or a context line
+    pub original: Option<OriginalLine>, // The line before it was decoded, kept for -b
 }
 
 impl SearchMatch {
@@ -12,14 +42,101 @@ impl SearchMatch {
     pub fn new(
         file: &str,
         line_number: usize,
-        line_content: String,
-        matches: Vec<(usize, usize)>,
+        byte_offset: usize,
+        line_content: Vec<u8>,
+        matches: Vec<MatchSpan>,
     ) -> Self {
         Self {
             file: file.to_owned(),
             line_number,
+            byte_offset,
             line_content,
             matches,
+            kind: MatchKind::Match,
+            original: None,
+        }
+    }
+
+    // Constructor for a line shown as context around a match
+    pub fn context(
+        file: &str,
+        line_number: usize,
+        byte_offset: usize,
+        line_content: Vec<u8>,
+    ) -> Self {
+        Self {
+            file: file.to_owned(),
+            line_number,
+            byte_offset,
+            line_content,
+            matches: Vec::new(),
+            kind: MatchKind::Context,
+            original: None,
         }
     }
+
+    pub fn is_context(&self) -> bool {
+        self.kind == MatchKind::Context
+    }
+
+    // 1-based column of a byte offset in the line, counted in characters
+    pub fn column(&self, offset: usize) -> usize {
+        String::from_utf8_lossy(&self.line_content[..offset])
+            .chars()
+            .count()
+            + 1
+    }
+
+    // Offset in the file of a byte offset in the line, counted in bytes of
+    // the original encoding when the line was decoded
+    pub fn file_offset(&self, offset: usize) -> usize {
+        match &self.original {
+            Some(original) => self.byte_offset + original.original_len(offset),
+            None => self.byte_offset + offset,
+        }
+    }
+
+    // The line with every match swapped for its replacement, if any
+    pub fn replaced_line(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut last_end = 0;
+
+        for span in &self.matches {
+            output.extend_from_slice(&self.line_content[last_end..span.start]);
+            match &span.replacement {
+                Some(replacement) => output.extend_from_slice(replacement),
+                None => output.extend_from_slice(&self.line_content[span.start..span.end]),
+            }
+            last_end = span.end;
+        }
+
+        output.extend_from_slice(&self.line_content[last_end..]);
+        output
+    }
+}
+
+// Statistics gathered while searching one or more files
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub matched_lines: usize,
+    pub matches: usize,
+    pub bytes_searched: usize,
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    pub fn has_match(&self) -> bool {
+        self.files_matched > 0
+    }
+
+    // Accumulate the statistics of another search, elapsed time is left to the caller
+    pub fn add(&mut self, other: &Stats) {
+        self.files_searched += other.files_searched;
+        self.files_matched += other.files_matched;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+        self.bytes_searched += other.bytes_searched;
+    }
 }
@@ -1,13 +1,35 @@
-use crate::app::flags::Flags;
+use crate::app::flags::{Flags, GlobFlag, SortFlag, SortKey};
+use crate::error::Error;
+use crate::plugin_integration::lua_plugin::LuaHooks;
+use crate::search::decompress::decompress;
+use crate::search::editor::{edit_file, EditOutcome, EditSummary};
+use crate::search::encoding::encoding_for_label;
+use crate::search::matcher::{
+    compile_matcher, search_file_for_patterns, search_reader_for_patterns, BinaryMode, Matcher,
+};
+use crate::search::result::Stats;
+use crate::search::sink::{Recorder, Sink};
+use encoding_rs::Encoding;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::types::{FileTypeDef, Types, TypesBuilder};
 use rayon::prelude::*;
-use std::io;
-use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Instant, SystemTime};
 
-use super::matcher::search_file_for_patterns;
+/// The path that stands for standard input.
+pub const STDIN_PATH: &str = "-";
+
+/// The name reported for standard input unless a label is set.
+pub const STDIN_LABEL: &str = "<stdin>";
 
 pub struct SearchResult {
     pub has_match: bool,
+    pub stats: Stats,
 }
 
 impl SearchResult {
@@ -17,90 +39,807 @@ impl SearchResult {
     }
 }
 
-/// Function to search files in a single-threaded manner
-pub fn search_files(
-    needle: &str,
-    files: &[String],
-    flags: &Flags,
-    walker: &ignore::WalkBuilder,
-    matched: &AtomicBool,
-) -> Result<SearchResult, Box<dyn std::error::Error>> {
-    let mut has_any_match = false;
+/// Configures and builds a [`Searcher`].
+///
+/// ```no_run
+/// use seekr::{JsonPrinter, SearcherBuilder};
+///
+/// let searcher = SearcherBuilder::new()
+///     .pattern("TODO")
+///     .ignore_case(true)
+///     .build()
+///     .unwrap();
+/// let result = searcher
+///     .search_paths(&[".".to_string()], &mut JsonPrinter::new())
+///     .unwrap();
+/// println!("found a match: {}", result.has_match());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearcherBuilder {
+    patterns: Vec<String>,
+    flags: Flags,
+    lua_hooks: Option<String>,
+}
 
-    for file in files {
-        let path = Path::new(file);
-
-        if path.is_dir() {
-            for entry in walker.build().filter_map(Result::ok) {
-                if entry.path().is_file() {
-                    if let Ok(has_match) = search_file(needle, entry.path(), flags) {
-                        if has_match {
-                            matched.store(true, Ordering::SeqCst);
-                            has_any_match = true;
-                        }
-                    }
-                }
+impl SearcherBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a pattern to search for. A line matches if any pattern matches.
+    pub fn pattern(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    /// Add several patterns to search for.
+    pub fn patterns<I, P>(&mut self, patterns: I) -> &mut Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        self.patterns.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Replace all flags at once, e.g. with the ones parsed from the command line.
+    pub fn flags(&mut self, flags: Flags) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn ignore_case(&mut self, yes: bool) -> &mut Self {
+        self.flags.ignore_case.set_enabled(yes);
+        self
+    }
+
+    /// Ignore case for patterns without uppercase letters.
+    pub fn smart_case(&mut self, yes: bool) -> &mut Self {
+        self.flags.smart_case.set_enabled(yes);
+        self
+    }
+
+    pub fn invert_match(&mut self, yes: bool) -> &mut Self {
+        self.flags.invert_match.set_enabled(yes);
+        self
+    }
+
+    /// Treat the patterns as literal strings instead of regexes.
+    pub fn fixed_strings(&mut self, yes: bool) -> &mut Self {
+        self.flags.fixed_strings.set_enabled(yes);
+        self
+    }
+
+    /// Only match patterns as whole words.
+    pub fn word_regexp(&mut self, yes: bool) -> &mut Self {
+        self.flags.word_regexp.set_enabled(yes);
+        self
+    }
+
+    /// Only match patterns against whole lines.
+    pub fn line_regexp(&mut self, yes: bool) -> &mut Self {
+        self.flags.line_regexp.set_enabled(yes);
+        self
+    }
+
+    /// Number of lines to report before and after each match.
+    pub fn context(&mut self, before: usize, after: usize) -> &mut Self {
+        self.flags.before_context = before;
+        self.flags.after_context = after;
+        self
+    }
+
+    /// Stop after the first match in each file.
+    pub fn list_files(&mut self, yes: bool) -> &mut Self {
+        self.flags.list_files.set_enabled(yes);
+        self
+    }
+
+    /// Name to report for standard input instead of `<stdin>`.
+    pub fn label(&mut self, label: Option<String>) -> &mut Self {
+        self.flags.label = label;
+        self
+    }
+
+    /// Search files one after another on the calling thread.
+    pub fn sequential(&mut self, yes: bool) -> &mut Self {
+        self.flags.sequential.set_enabled(yes);
+        self
+    }
+
+    /// Report files in a fixed order instead of as soon as they are searched.
+    pub fn sort(&mut self, sort: Option<SortFlag>) -> &mut Self {
+        self.flags.sort = sort;
+        self
+    }
+
+    /// Stop reading a file after this many matching lines.
+    pub fn max_count(&mut self, max: Option<usize>) -> &mut Self {
+        self.flags.max_count = max;
+        self
+    }
+
+    /// Stop the whole search after this many matching lines.
+    pub fn max_total(&mut self, max: Option<usize>) -> &mut Self {
+        self.flags.max_total = max;
+        self
+    }
+
+    /// Decode input from this encoding, a WHATWG label like `utf-16le`.
+    /// A byte order mark at the start of the input takes precedence.
+    pub fn encoding(&mut self, label: Option<String>) -> &mut Self {
+        self.flags.encoding = label;
+        self
+    }
+
+    /// Decompress gzip, xz, bzip2, zstd and lz4 input while searching it.
+    pub fn search_zip(&mut self, yes: bool) -> &mut Self {
+        self.flags.search_zip.set_enabled(yes);
+        self
+    }
+
+    /// Limit how deep directories are walked.
+    pub fn max_depth(&mut self, depth: Option<usize>) -> &mut Self {
+        self.flags.max_depth = depth;
+        self
+    }
+
+    /// Also walk hidden files and directories.
+    pub fn hidden(&mut self, yes: bool) -> &mut Self {
+        self.flags.hidden.set_enabled(yes);
+        self
+    }
+
+    /// Run the `filter` and `transform` hooks of a Lua script on every match.
+    pub fn lua_hooks(&mut self, script_path: Option<String>) -> &mut Self {
+        self.lua_hooks = script_path;
+        self
+    }
+
+    /// Only search files matching a gitignore style glob, `!` excludes
+    /// instead. Later globs take precedence over earlier ones.
+    pub fn glob(&mut self, glob: impl Into<String>, case_insensitive: bool) -> &mut Self {
+        self.flags.globs.push(GlobFlag {
+            glob: glob.into(),
+            case_insensitive,
+        });
+        self
+    }
+
+    /// Only search files of a type, e.g. `rust`. Can be given multiple times.
+    pub fn file_type(&mut self, name: impl Into<String>) -> &mut Self {
+        self.flags.types.push(name.into());
+        self
+    }
+
+    /// Do not search files of a type.
+    pub fn file_type_not(&mut self, name: impl Into<String>) -> &mut Self {
+        self.flags.types_not.push(name.into());
+        self
+    }
+
+    /// Define a file type or add a glob to one, e.g. `proto:*.proto`.
+    pub fn file_type_add(&mut self, def: impl Into<String>) -> &mut Self {
+        self.flags.type_adds.push(def.into());
+        self
+    }
+
+    pub fn build(&self) -> Result<Searcher, Error> {
+        let matcher = compile_matcher(&self.patterns, &self.flags)?;
+        let encoding = match &self.flags.encoding {
+            Some(label) => {
+                Some(encoding_for_label(label).ok_or_else(|| Error::Encoding(label.clone()))?)
             }
-        } else if path.is_file() {
-            if let Ok(has_match) = search_file(needle, path, flags) {
-                if has_match {
-                    matched.store(true, Ordering::SeqCst);
-                    has_any_match = true;
+            None => None,
+        };
+
+        let searcher = Searcher {
+            matcher,
+            encoding,
+            types: build_types(&self.flags)?,
+            flags: self.flags.clone(),
+            lua_hooks: self.lua_hooks.as_deref().map(LuaHooks::new),
+            total_matches: Arc::default(),
+            matched: Arc::default(),
+            unmatched: Arc::default(),
+        };
+        // Report invalid globs now rather than once per walked directory
+        searcher.overrides(".")?;
+
+        Ok(searcher)
+    }
+}
+
+/// Searches files, directories and readers, reporting results to a [`Sink`].
+#[derive(Debug, Clone)]
+pub struct Searcher {
+    matcher: Matcher,
+    encoding: Option<&'static Encoding>,
+    types: Types,
+    flags: Flags,
+    lua_hooks: Option<LuaHooks>,
+    // Matching lines reported by the running search, for --max-total.
+    // Clones of a searcher share the count.
+    total_matches: Arc<AtomicUsize>,
+    // Set once the running search found a file with and without a match,
+    // tells --quiet workers to stop. Clones of a searcher share them too.
+    matched: Arc<AtomicBool>,
+    unmatched: Arc<AtomicBool>,
+}
+
+impl Searcher {
+    pub fn flags(&self) -> &Flags {
+        &self.flags
+    }
+
+    pub fn matcher(&self) -> &Matcher {
+        &self.matcher
+    }
+
+    /// The encoding set with `--encoding`, if any.
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        self.encoding
+    }
+
+    pub fn lua_hooks(&self) -> Option<&LuaHooks> {
+        self.lua_hooks.as_ref()
+    }
+
+    /// Count a matching line against `--max-total`, returns false once the
+    /// limit is used up and the line must not be reported.
+    pub fn reserve_match(&self) -> bool {
+        match self.flags.max_total {
+            Some(max) => self.total_matches.fetch_add(1, Ordering::SeqCst) < max,
+            None => true,
+        }
+    }
+
+    /// Returns true once `--max-total` matching lines have been reported.
+    pub fn max_total_reached(&self) -> bool {
+        self.flags
+            .max_total
+            .is_some_and(|max| self.total_matches.load(Ordering::Relaxed) >= max)
+    }
+
+    // Matching lines --max-total still allows, if it is set
+    fn remaining_matches(&self) -> Option<usize> {
+        let used = self.total_matches.load(Ordering::SeqCst);
+        self.flags.max_total.map(|max| max.saturating_sub(used))
+    }
+
+    // A copy for searching files ahead of the output in sorted mode. It
+    // leaves --max-total to the caller, but reads no more than that many
+    // matching lines of a file.
+    fn without_max_total(&self) -> Searcher {
+        let mut searcher = self.clone();
+        if let Some(max) = searcher.flags.max_total.take() {
+            let max_count = searcher.flags.max_count.map_or(max, |count| count.min(max));
+            searcher.flags.max_count = Some(max_count);
+        }
+        searcher.total_matches = Arc::default();
+        searcher
+    }
+
+    /// Returns true once the search can end before every line is read: with
+    /// `--quiet` once anything matched, or with `-L` once a file did not
+    /// match, and once `--max-total` is reached.
+    pub fn is_done(&self) -> bool {
+        let settled = if self.flags.files_without_match.is_enabled() {
+            &self.unmatched
+        } else {
+            &self.matched
+        };
+        (self.flags.quiet.is_enabled() && settled.load(Ordering::Relaxed))
+            || self.max_total_reached()
+    }
+
+    // Note whether a searched file matched, for has_match and is_done
+    fn record_outcome(&self, file_stats: &Stats) {
+        if file_stats.has_match() {
+            self.matched.store(true, Ordering::SeqCst);
+        } else {
+            self.unmatched.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Build the directory walker used for a directory.
+    pub fn walk_builder(&self, path: &str) -> Result<ignore::WalkBuilder, Error> {
+        let mut builder = ignore::WalkBuilder::new(path);
+
+        builder
+            .max_depth(self.flags.max_depth)
+            .hidden(!self.flags.hidden.is_enabled())
+            .overrides(self.overrides(path)?)
+            .types(self.types.clone());
+
+        Ok(builder)
+    }
+
+    // Build the -g/--iglob filters, globs are relative to the walked directory
+    fn overrides(&self, root: &str) -> Result<Override, Error> {
+        let mut builder = OverrideBuilder::new(root);
+        for glob in &self.flags.globs {
+            builder.case_insensitive(glob.case_insensitive)?;
+            builder.add(&glob.glob)?;
+        }
+
+        Ok(builder.build()?)
+    }
+
+    // Matching binary files are reported without their lines unless --text
+    // is given, --binary searches them to the end
+    fn binary_mode(&self) -> BinaryMode {
+        if self.flags.text.is_enabled() {
+            BinaryMode::Text
+        } else if self.flags.binary.is_enabled() {
+            BinaryMode::Search
+        } else {
+            BinaryMode::Report
+        }
+    }
+
+    /// Search a single file.
+    pub fn search_path<S: Sink>(&self, path: &Path, sink: &mut S) -> Result<Stats, Error> {
+        let file_path = path.to_string_lossy();
+        search_file_for_patterns(self, &file_path, self.binary_mode(), sink)
+    }
+
+    /// Search everything readable from `reader`, reporting it as `label`.
+    pub fn search_reader<R: Read, S: Sink>(
+        &self,
+        label: &str,
+        reader: R,
+        sink: &mut S,
+    ) -> Result<Stats, Error> {
+        let reader = BufReader::new(reader);
+        search_reader_for_patterns(self, label, reader, self.binary_mode(), sink)
+    }
+
+    /// Search standard input, reported under the configured label.
+    pub fn search_stdin<S: Sink>(&self, sink: &mut S) -> Result<Stats, Error> {
+        let label = self.flags.label.as_deref().unwrap_or(STDIN_LABEL);
+        let stdin = std::io::stdin();
+        let binary = self.binary_mode();
+
+        if self.flags.search_zip.is_enabled() {
+            let reader = decompress(stdin.lock()).map_err(|err| Error::file(label, err))?;
+            return search_reader_for_patterns(self, label, reader, binary, sink);
+        }
+        search_reader_for_patterns(self, label, stdin.lock(), binary, sink)
+    }
+
+    /// Search files and directories, in parallel unless the searcher is sequential.
+    pub fn search_paths<S: Sink>(
+        &self,
+        paths: &[String],
+        sink: &mut S,
+    ) -> Result<SearchResult, Error> {
+        self.matched.store(false, Ordering::SeqCst);
+        self.unmatched.store(false, Ordering::SeqCst);
+        self.total_matches.store(0, Ordering::SeqCst);
+        // Nothing is printed with --quiet, so there is nothing to sort either
+        let sort = self.flags.sort.filter(|_| !self.flags.quiet.is_enabled());
+        if let Some(sort) = sort {
+            search_files_sorted(self, paths, sort, sink)
+        } else if self.flags.sequential.is_enabled() {
+            search_files(self, paths, sink)
+        } else {
+            search_files_parallel(self, paths, sink)
+        }
+    }
+
+    /// Replace the matches in files and directories in place, see [`edit_files`].
+    pub fn edit_paths<F>(&self, paths: &[String], report: F) -> Result<EditSummary, Error>
+    where
+        F: FnMut(&EditOutcome) -> Result<(), Error>,
+    {
+        edit_files(self, paths, report)
+    }
+}
+
+// Start from the default file types plus the user defined ones
+fn types_builder(flags: &Flags) -> Result<TypesBuilder, Error> {
+    let mut builder = TypesBuilder::new();
+    builder.add_defaults();
+    for def in &flags.type_adds {
+        builder.add_def(def)?;
+    }
+
+    Ok(builder)
+}
+
+fn build_types(flags: &Flags) -> Result<Types, Error> {
+    let mut builder = types_builder(flags)?;
+
+    if flags.types.is_empty() {
+        builder.select("all");
+    }
+    for name in &flags.types {
+        builder.select(name);
+    }
+    for name in &flags.types_not {
+        builder.negate(name);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// All known file types, including the ones added with `--type-add`.
+pub fn type_definitions(flags: &Flags) -> Result<Vec<FileTypeDef>, Error> {
+    Ok(types_builder(flags)?.definitions())
+}
+
+// Files that cannot be read are skipped, anything else stops the search
+fn skip_unreadable(result: Result<Stats, Error>) -> Result<Stats, Error> {
+    match result {
+        Err(Error::File { .. }) => Ok(Stats::default()),
+        result => result,
+    }
+}
+
+// Visit a file, or every file the walker finds below a directory, until
+// `stop` returns true
+fn for_each_file<F>(
+    searcher: &Searcher,
+    file: &str,
+    stop: &dyn Fn() -> bool,
+    mut visit: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&Path) -> Result<(), Error>,
+{
+    let path = Path::new(file);
+
+    if path.is_dir() {
+        let walker = searcher.walk_builder(file)?;
+        for entry in walker.build().filter_map(Result::ok) {
+            if stop() {
+                break;
+            }
+            if entry.path().is_file() {
+                visit(entry.path())?;
+            }
+        }
+    } else if path.is_file() {
+        visit(path)?;
+    }
+
+    Ok(())
+}
+
+// Run `work` on every file below `files` on the rayon pool and hand the
+// results to `handle` on the calling thread, in the order they come in.
+// Files that cannot be read are skipped. Once `stop` returns true the
+// workers stop walking and no further files are started.
+fn for_each_file_parallel<T, W, H>(
+    searcher: &Searcher,
+    files: &[&String],
+    stop: &(dyn Fn() -> bool + Sync),
+    work: W,
+    mut handle: H,
+) -> Result<(), Error>
+where
+    T: Send,
+    W: Fn(&Path) -> Result<T, Error> + Sync,
+    H: FnMut(T) -> Result<(), Error>,
+{
+    let (sender, receiver) = mpsc::channel::<Result<T, Error>>();
+    let work = &work;
+
+    std::thread::scope(|scope| {
+        // Sending fails once the receiver hung up after an error, which
+        // stops the workers as well
+        let send = move |sender: &mut mpsc::Sender<Result<T, Error>>, path: &Path| {
+            if stop() {
+                return Err(());
+            }
+            sender.send(work(path)).map_err(drop)
+        };
+
+        scope.spawn(move || {
+            let _ = files.par_iter().try_for_each_with(sender, |sender, file| {
+                let path = Path::new(file);
+
+                if path.is_dir() {
+                    let walker = match searcher.walk_builder(file) {
+                        Ok(walker) => walker,
+                        Err(err) => return sender.send(Err(err)).map_err(drop),
+                    };
+
+                    walker
+                        .build()
+                        .par_bridge()
+                        .filter_map(Result::ok)
+                        .filter(|entry| entry.path().is_file())
+                        .try_for_each_with(sender.clone(), |sender, entry| {
+                            send(sender, entry.path())
+                        })
+                } else if path.is_file() {
+                    send(sender, path)
+                } else {
+                    Ok(())
                 }
+            });
+        });
+
+        for result in receiver {
+            match result {
+                Ok(result) => handle(result)?,
+                Err(Error::File { .. }) => continue,
+                Err(err) => return Err(err),
             }
         }
+
+        Ok(())
+    })
+}
+
+/// Function to search files in a single-threaded manner
+pub fn search_files<S: Sink>(
+    searcher: &Searcher,
+    files: &[String],
+    sink: &mut S,
+) -> Result<SearchResult, Error> {
+    let started = Instant::now();
+    let mut stats = Stats::default();
+    let stop = || searcher.is_done();
+
+    for file in files {
+        if stop() {
+            break;
+        }
+
+        if file == STDIN_PATH {
+            let file_stats = searcher.search_stdin(sink)?;
+            stats.add(&file_stats);
+            searcher.record_outcome(&file_stats);
+            continue;
+        }
+
+        for_each_file(searcher, file, &stop, |path| {
+            let file_stats = skip_unreadable(searcher.search_path(path, &mut *sink))?;
+            stats.add(&file_stats);
+            searcher.record_outcome(&file_stats);
+            Ok(())
+        })?;
     }
 
+    stats.elapsed = started.elapsed();
     Ok(SearchResult {
-        has_match: has_any_match,
+        has_match: searcher.matched.load(Ordering::SeqCst),
+        stats,
     })
 }
 
 /// Function to search files in parallel
-pub fn search_files_parallel(
-    needle: &str,
+///
+/// Files are searched on the rayon pool and recorded, then replayed into the
+/// sink on the calling thread so the sink does not need to be thread safe.
+pub fn search_files_parallel<S: Sink>(
+    searcher: &Searcher,
+    files: &[String],
+    sink: &mut S,
+) -> Result<SearchResult, Error> {
+    let started = Instant::now();
+    let mut stats = Stats::default();
+
+    // Standard input is streamed straight to the sink instead of being recorded
+    let (stdin, files): (Vec<&String>, Vec<&String>) =
+        files.iter().partition(|file| *file == STDIN_PATH);
+    if !stdin.is_empty() {
+        let file_stats = searcher.search_stdin(sink)?;
+        stats.add(&file_stats);
+        searcher.record_outcome(&file_stats);
+    }
+
+    let stop = || searcher.is_done();
+    let search = |path: &Path| {
+        let mut recorder = Recorder::default();
+        let file_stats = searcher.search_path(path, &mut recorder)?;
+        // Let the other workers know right away rather than once replayed
+        searcher.record_outcome(&file_stats);
+        Ok((file_stats, recorder))
+    };
+
+    for_each_file_parallel(searcher, &files, &stop, search, |(file_stats, recorder)| {
+        recorder.replay(&mut *sink)?;
+        stats.add(&file_stats);
+        searcher.record_outcome(&file_stats);
+        Ok(())
+    })?;
+
+    stats.elapsed = started.elapsed();
+    Ok(SearchResult {
+        has_match: searcher.matched.load(Ordering::SeqCst),
+        stats,
+    })
+}
+
+/// Function to search files and print the results in a fixed order
+///
+/// Every file is walked and sorted up front. The files are then searched in
+/// parallel, unless the searcher is sequential, and results that finish early
+/// are held back until all files sorted before them have been replayed.
+pub fn search_files_sorted<S: Sink>(
+    searcher: &Searcher,
     files: &[String],
-    flags: &Flags,
-    walker: &ignore::WalkBuilder,
-    matched: &AtomicBool,
-) -> Result<SearchResult, Box<dyn std::error::Error>> {
-    let has_any_match = AtomicBool::new(false);
-
-    files.par_iter().for_each(|file| {
-        let path = Path::new(file);
-
-        if path.is_dir() {
-            walker
-                .build()
-                .par_bridge()
-                .filter_map(Result::ok)
-                .for_each(|entry| {
-                    if entry.path().is_file() {
-                        if let Ok(has_match) = search_file(needle, entry.path(), flags) {
-                            if has_match {
-                                matched.store(true, Ordering::SeqCst);
-                                has_any_match.store(true, Ordering::SeqCst);
-                            }
+    sort: SortFlag,
+    sink: &mut S,
+) -> Result<SearchResult, Error> {
+    let started = Instant::now();
+    let mut stats = Stats::default();
+
+    // Standard input has nothing to sort by, it always comes first
+    let mut sorted = Vec::new();
+    for file in files {
+        if file == STDIN_PATH {
+            let file_stats = searcher.search_stdin(sink)?;
+            stats.add(&file_stats);
+            searcher.record_outcome(&file_stats);
+            continue;
+        }
+
+        for_each_file(searcher, file, &|| false, |path| {
+            sorted.push(path.to_path_buf());
+            Ok(())
+        })?;
+    }
+    sort_files(&mut sorted, sort);
+
+    // Files are searched ahead of the output, so --max-total is applied
+    // here while replaying them in order
+    let ahead = searcher.without_max_total();
+    let stop = || searcher.is_done();
+
+    let mut handle = |path: &PathBuf, file_stats: Stats, recorder: Recorder| -> Result<(), Error> {
+        if stop() {
+            return Ok(());
+        }
+
+        let file_stats = match searcher.remaining_matches() {
+            // This file uses up --max-total, search it again so it stops at
+            // the same line as an unsorted search would
+            Some(remaining) if file_stats.matched_lines >= remaining => {
+                skip_unreadable(searcher.search_path(path, &mut *sink))?
+            }
+            _ => {
+                searcher
+                    .total_matches
+                    .fetch_add(file_stats.matched_lines, Ordering::SeqCst);
+                recorder.replay(&mut *sink)?;
+                file_stats
+            }
+        };
+
+        stats.add(&file_stats);
+        searcher.record_outcome(&file_stats);
+        Ok(())
+    };
+
+    let search = |path: &PathBuf| {
+        let mut recorder = Recorder::default();
+        ahead
+            .search_path(path, &mut recorder)
+            .map(|file_stats| (file_stats, recorder))
+    };
+
+    if searcher.flags.sequential.is_enabled() {
+        for entry in &sorted {
+            if stop() {
+                break;
+            }
+            match search(entry) {
+                Ok((file_stats, recorder)) => handle(entry, file_stats, recorder)?,
+                Err(Error::File { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    } else {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                // The receiver only hangs up after an error
+                let _ = sorted.par_iter().enumerate().try_for_each_with(
+                    sender,
+                    |sender, (index, entry)| {
+                        if stop() {
+                            return Err(());
                         }
+                        sender.send((index, search(entry))).map_err(drop)
+                    },
+                );
+            });
+
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (index, searched) in receiver {
+                pending.insert(index, searched);
+                while let Some(searched) = pending.remove(&next) {
+                    match searched {
+                        Ok((file_stats, recorder)) => handle(&sorted[next], file_stats, recorder)?,
+                        Err(Error::File { .. }) => {}
+                        Err(err) => return Err(err),
                     }
-                });
-        } else if path.is_file() {
-            if let Ok(has_match) = search_file(needle, path, flags) {
-                if has_match {
-                    matched.store(true, Ordering::SeqCst);
-                    has_any_match.store(true, Ordering::SeqCst);
+                    next += 1;
                 }
             }
-        }
-    });
 
+            Ok(())
+        })?;
+    }
+
+    stats.elapsed = started.elapsed();
     Ok(SearchResult {
-        has_match: has_any_match.load(Ordering::SeqCst),
+        has_match: searcher.matched.load(Ordering::SeqCst),
+        stats,
     })
 }
 
-/// Helper function to search within a file
-fn search_file(needle: &str, path: &Path, flags: &Flags) -> Result<bool, io::Error> {
-    let file_path = path.to_string_lossy();
-    search_file_for_patterns(needle, &file_path, flags)
+fn sort_files(files: &mut [PathBuf], sort: SortFlag) {
+    match sort.key {
+        SortKey::Path => files.sort(),
+        SortKey::Modified => sort_by_time(files, Metadata::modified),
+        SortKey::Accessed => sort_by_time(files, Metadata::accessed),
+        SortKey::Created => sort_by_time(files, Metadata::created),
+    }
+
+    if sort.reverse {
+        files.reverse();
+    }
+}
+
+// Files whose time cannot be read, e.g. creation time on some file systems,
+// sort before all others
+fn sort_by_time(files: &mut [PathBuf], time: fn(&Metadata) -> io::Result<SystemTime>) {
+    files.sort_by_cached_key(|path| fs::metadata(path).and_then(|meta| time(&meta)).ok());
+}
+
+/// Function to replace matches in files in place, in parallel unless the
+/// searcher is sequential.
+///
+/// Every file is read, rewritten and written back by the worker that
+/// searched it. The outcome for each file is handed to `report` on the
+/// calling thread.
+pub fn edit_files<F>(
+    searcher: &Searcher,
+    files: &[String],
+    mut report: F,
+) -> Result<EditSummary, Error>
+where
+    F: FnMut(&EditOutcome) -> Result<(), Error>,
+{
+    if files.iter().any(|file| file == STDIN_PATH) {
+        let err = io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "standard input cannot be edited in place",
+        );
+        return Err(Error::file(STDIN_LABEL, err));
+    }
+
+    let mut summary = EditSummary::default();
+    let mut handle = |outcome: EditOutcome| {
+        if let EditOutcome::Edited(edit) = &outcome {
+            summary.add(edit);
+        }
+        report(&outcome)
+    };
+
+    if searcher.flags.sequential.is_enabled() {
+        for file in files {
+            for_each_file(searcher, file, &|| false, |path| {
+                match edit_file(searcher, path) {
+                    Ok(outcome) => handle(outcome),
+                    Err(Error::File { .. }) => Ok(()),
+                    Err(err) => Err(err),
+                }
+            })?;
+        }
+    } else {
+        let files: Vec<&String> = files.iter().collect();
+        let edit = |path: &Path| edit_file(searcher, path);
+        for_each_file_parallel(searcher, &files, &|| false, edit, handle)?;
+    }
+
+    Ok(summary)
 }
@@ -0,0 +1,147 @@
+use crate::error::Error;
+use crate::search::result::{SearchMatch, Stats};
+
+/// Receives the results of a search.
+///
+/// For every searched file `begin` is called first, then `matched`,
+/// `context` and `context_break` in line order, and `finish` last.
+/// Returning an error stops the search.
+pub trait Sink {
+    /// Called before a file is searched.
+    fn begin(&mut self, _path: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called for every line selected by the search.
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error>;
+
+    /// Called for every line shown as context around a match.
+    fn context(&mut self, _result: &SearchMatch) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called between two groups of lines that are not adjacent.
+    fn context_break(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called instead of `matched` when a file that looks binary matches.
+    /// `byte_offset` is where the first NUL byte was found.
+    fn binary_matched(&mut self, _path: &str, _byte_offset: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once a file has been searched.
+    fn finish(&mut self, _path: &str, _stats: &Stats) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn begin(&mut self, path: &str) -> Result<(), Error> {
+        (**self).begin(path)
+    }
+
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        (**self).matched(result)
+    }
+
+    fn context(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        (**self).context(result)
+    }
+
+    fn context_break(&mut self) -> Result<(), Error> {
+        (**self).context_break()
+    }
+
+    fn binary_matched(&mut self, path: &str, byte_offset: usize) -> Result<(), Error> {
+        (**self).binary_matched(path, byte_offset)
+    }
+
+    fn finish(&mut self, path: &str, stats: &Stats) -> Result<(), Error> {
+        (**self).finish(path, stats)
+    }
+}
+
+#[derive(Debug)]
+enum SinkEvent {
+    Begin(String),
+    Matched(SearchMatch),
+    Context(SearchMatch),
+    ContextBreak,
+    BinaryMatched(String, usize),
+    Finish(String, Stats),
+}
+
+/// A sink that stores everything it receives so it can be replayed later.
+///
+/// The parallel searcher records each file on a worker thread and replays
+/// it into the real sink, which keeps the output of a file together.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    events: Vec<SinkEvent>,
+}
+
+impl Recorder {
+    /// Send the recorded events to another sink, in the order they arrived.
+    pub fn replay<S: Sink>(self, sink: &mut S) -> Result<(), Error> {
+        for event in self.events {
+            match event {
+                SinkEvent::Begin(path) => sink.begin(&path)?,
+                SinkEvent::Matched(result) => sink.matched(&result)?,
+                SinkEvent::Context(result) => sink.context(&result)?,
+                SinkEvent::ContextBreak => sink.context_break()?,
+                SinkEvent::BinaryMatched(path, byte_offset) => {
+                    sink.binary_matched(&path, byte_offset)?
+                }
+                SinkEvent::Finish(path, stats) => sink.finish(&path, &stats)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Sink for Recorder {
+    fn begin(&mut self, path: &str) -> Result<(), Error> {
+        self.events.push(SinkEvent::Begin(path.to_owned()));
+        Ok(())
+    }
+
+    fn matched(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        self.events.push(SinkEvent::Matched(result.clone()));
+        Ok(())
+    }
+
+    fn context(&mut self, result: &SearchMatch) -> Result<(), Error> {
+        self.events.push(SinkEvent::Context(result.clone()));
+        Ok(())
+    }
+
+    fn context_break(&mut self) -> Result<(), Error> {
+        self.events.push(SinkEvent::ContextBreak);
+        Ok(())
+    }
+
+    fn binary_matched(&mut self, path: &str, byte_offset: usize) -> Result<(), Error> {
+        self.events
+            .push(SinkEvent::BinaryMatched(path.to_owned(), byte_offset));
+        Ok(())
+    }
+
+    fn finish(&mut self, path: &str, stats: &Stats) -> Result<(), Error> {
+        self.events
+            .push(SinkEvent::Finish(path.to_owned(), stats.clone()));
+        Ok(())
+    }
+}
+
+/// Ignores everything, for when only the outcome of a search matters.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Discard;
+
+impl Sink for Discard {
+    fn matched(&mut self, _result: &SearchMatch) -> Result<(), Error> {
+        Ok(())
+    }
+}